@@ -0,0 +1,159 @@
+//! Rows per second: parsing the CSV (via serde, and on the fast path),
+//! decoding the binary format,
+//! processing the transactions by the engine, and the CLI end-to-end; for each
+//! of the [`workload::WORKLOADS`].
+
+use std::{
+    hint::black_box,
+    io::Write,
+    process::{Command, Stdio},
+};
+
+use balances::{
+    engine::Engine,
+    input::{
+        ByteRecordParser, Tx,
+        binary::{BinaryRows, BinaryWriter},
+    },
+};
+use criterion::{BatchSize, Criterion, Throughput, criterion_group, criterion_main};
+use csv::ByteRecord;
+
+mod workload;
+
+fn parse_csv(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse_csv");
+    for workload in workload::WORKLOADS {
+        let csv = workload.csv();
+        group.throughput(Throughput::Elements(workload.rows.into()));
+        group.bench_function(workload.name, |b| {
+            b.iter(|| {
+                let csv_reader = csv::ReaderBuilder::new()
+                    .trim(csv::Trim::All)
+                    .flexible(true)
+                    .from_reader(csv.as_bytes());
+                for tx in csv_reader.into_deserialize::<Tx>() {
+                    let _ = black_box(tx);
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+fn parse_byte_records(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse_byte_records");
+    for workload in workload::WORKLOADS {
+        let csv = workload.csv();
+        group.throughput(Throughput::Elements(workload.rows.into()));
+        group.bench_function(workload.name, |b| {
+            b.iter(|| {
+                let mut csv_reader = csv::ReaderBuilder::new()
+                    .trim(csv::Trim::All)
+                    .flexible(true)
+                    .from_reader(csv.as_bytes());
+                let parser = ByteRecordParser::new(csv_reader.byte_headers().unwrap());
+                let mut record = ByteRecord::new();
+                while csv_reader.read_byte_record(&mut record).unwrap() {
+                    let _ = black_box(parser.parse(&record));
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+fn parse_binary(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse_binary");
+    for workload in workload::WORKLOADS {
+        let mut binary_writer = BinaryWriter::new(vec![]).unwrap();
+        for tx in workload.transactions() {
+            binary_writer.write(&tx).unwrap();
+        }
+        let binary = binary_writer.into_inner().unwrap();
+        group.throughput(Throughput::Elements(workload.rows.into()));
+        group.bench_function(workload.name, |b| {
+            b.iter(|| {
+                for row in BinaryRows::new(binary.as_slice()).unwrap() {
+                    let _ = black_box(row.unwrap());
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+fn process_tx(c: &mut Criterion) {
+    let mut group = c.benchmark_group("process_tx");
+    for workload in workload::WORKLOADS {
+        let transactions = workload.transactions();
+        group.throughput(Throughput::Elements(workload.rows.into()));
+        group.bench_function(workload.name, |b| {
+            b.iter_batched(
+                || {
+                    (
+                        Engine::with_tx_cache_size(workload.tx_cache_size),
+                        transactions.clone(),
+                    )
+                },
+                |(mut engine, transactions)| {
+                    for tx in transactions {
+                        let _ = black_box(engine.process_tx(tx));
+                    }
+                    engine
+                },
+                BatchSize::PerIteration,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn cli(c: &mut Criterion) {
+    let mut group = c.benchmark_group("cli");
+    group.sample_size(10);
+    for workload in workload::WORKLOADS {
+        let input_file = std::env::temp_dir().join(format!(
+            "balances-bench-{}-{}.csv",
+            std::process::id(),
+            workload.name
+        ));
+        std::fs::File::create(&input_file)
+            .and_then(|mut file| file.write_all(workload.csv().as_bytes()))
+            .expect("writing the input file");
+
+        group.throughput(Throughput::Elements(workload.rows.into()));
+        for parser_threads in [0, 2] {
+            let name = match parser_threads {
+                0 => workload.name.to_owned(),
+                _ => format!("{}-pipelined", workload.name),
+            };
+            group.bench_function(name, |b| {
+                b.iter(|| {
+                    let status = Command::new(env!("CARGO_BIN_EXE_balances"))
+                        .env("TX_LRU_SIZE", workload.tx_cache_size.to_string())
+                        .env("PARSER_THREADS", parser_threads.to_string())
+                        .arg(&input_file)
+                        .stdout(Stdio::null())
+                        .stderr(Stdio::null())
+                        .status()
+                        .expect("running the CLI");
+                    assert!(status.success());
+                })
+            });
+        }
+
+        let _ = std::fs::remove_file(&input_file);
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    parse_csv,
+    parse_byte_records,
+    parse_binary,
+    process_tx,
+    cli
+);
+criterion_main!(benches);
@@ -0,0 +1,180 @@
+//! The engine's throughput, with the transactions cache under pressure;
+//! compared with the baseline cache the slab replaced (a hash-map of the
+//! transactions, plus an LRU of the evictable tx-ids). Requires the `bench`
+//! feature. Also prints the memory each cache actually allocates per kept
+//! transaction, as counted by the global allocator.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    hint::black_box,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use balances::{
+    engine::Engine,
+    input::{Tx, TxDeposit, TxKind},
+    types::{Amount, PositiveAmount},
+};
+use criterion::{BatchSize, Criterion, Throughput, criterion_group};
+
+const TXS_COUNT: u32 = 200_000;
+const CLIENTS_COUNT: u32 = 1_000;
+const TX_CACHE_SIZE: usize = 16 * 1024;
+const FOOTPRINT_TX_CACHE_SIZE: u32 = 1024 * 1024;
+
+/// The system allocator, counting the bytes allocated (currently, and at
+/// peak).
+struct CountingAlloc;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED_PEAK: AtomicUsize = AtomicUsize::new(0);
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+impl CountingAlloc {
+    fn add(size: usize) {
+        let allocated = ALLOCATED.fetch_add(size, Ordering::Relaxed) + size;
+        ALLOCATED_PEAK.fetch_max(allocated, Ordering::Relaxed);
+    }
+
+    fn sub(size: usize) {
+        ALLOCATED.fetch_sub(size, Ordering::Relaxed);
+    }
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            Self::add(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc_zeroed(layout) };
+        if !ptr.is_null() {
+            Self::add(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        Self::sub(layout.size());
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = unsafe { System.realloc(ptr, layout, new_size) };
+        if !new_ptr.is_null() {
+            Self::sub(layout.size());
+            Self::add(new_size);
+        }
+        new_ptr
+    }
+}
+
+/// Deposits, every tenth of them disputed (and later resolved) while still
+/// cached.
+fn transactions() -> Vec<Tx> {
+    let mut transactions = vec![];
+    for tx_id in 0..TXS_COUNT {
+        transactions.push(deposit(tx_id));
+        if tx_id % 10 == 0 && tx_id >= 1_000 {
+            transactions.push(tx(
+                (tx_id - 1_000) % CLIENTS_COUNT,
+                tx_id - 1_000,
+                TxKind::Dispute,
+            ));
+        }
+        if tx_id % 10 == 0 && tx_id >= 2_000 {
+            transactions.push(tx(
+                (tx_id - 2_000) % CLIENTS_COUNT,
+                tx_id - 2_000,
+                TxKind::Resolve,
+            ));
+        }
+    }
+    transactions
+}
+
+fn deposit(tx_id: u32) -> Tx {
+    let amount = PositiveAmount::try_from(Amount::from_str_exact("1.5").unwrap()).unwrap();
+    tx(
+        tx_id % CLIENTS_COUNT,
+        tx_id,
+        TxKind::Deposit(TxDeposit {
+            amount_deposited: amount,
+        }),
+    )
+}
+
+fn tx(client_id: u32, tx_id: u32, kind: TxKind) -> Tx {
+    Tx {
+        client_id: u16::try_from(client_id).unwrap().into(),
+        tx_id: tx_id.into(),
+        kind,
+        timestamp: None,
+    }
+}
+
+/// The bytes allocated per kept transaction, with the cache filled four times
+/// over (the evicted transactions dropped): as the engine is left, and at
+/// peak (while a hash-map grows, both its old and new tables are allocated).
+fn footprint(name: &str, engine_with_tx_cache_size: fn(usize) -> Engine) {
+    let allocated_before = ALLOCATED.load(Ordering::Relaxed);
+    ALLOCATED_PEAK.store(allocated_before, Ordering::Relaxed);
+
+    let mut engine = engine_with_tx_cache_size(FOOTPRINT_TX_CACHE_SIZE as usize);
+    for tx_id in 0..4 * FOOTPRINT_TX_CACHE_SIZE {
+        let _ = engine.process_tx(deposit(tx_id));
+    }
+    let per_tx = |bytes: usize| bytes / FOOTPRINT_TX_CACHE_SIZE as usize;
+    println!(
+        "tx_cache/footprint/{name}: {}B per kept transaction, {}B at peak (estimated {}B)",
+        per_tx(ALLOCATED.load(Ordering::Relaxed) - allocated_before),
+        per_tx(ALLOCATED_PEAK.load(Ordering::Relaxed) - allocated_before),
+        per_tx(engine.memory_usage().transactions),
+    );
+}
+
+fn process_tx(c: &mut Criterion) {
+    let transactions = transactions();
+
+    let mut group = c.benchmark_group("tx_cache");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(transactions.len() as u64));
+    let engines: [(_, fn(_) -> _); 2] = [
+        ("slab", Engine::with_tx_cache_size),
+        ("baseline", Engine::with_baseline_tx_cache),
+    ];
+    for (name, engine_with_tx_cache_size) in engines {
+        footprint(name, engine_with_tx_cache_size);
+        group.bench_function(format!("process_tx/{name}"), |b| {
+            b.iter_batched(
+                || {
+                    (
+                        engine_with_tx_cache_size(TX_CACHE_SIZE),
+                        transactions.clone(),
+                    )
+                },
+                |(mut engine, transactions)| {
+                    for tx in transactions {
+                        let _ = black_box(engine.process_tx(tx));
+                    }
+                    engine
+                },
+                BatchSize::PerIteration,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, process_tx);
+
+fn main() {
+    benches();
+    Criterion::default().configure_from_args().final_summary();
+}
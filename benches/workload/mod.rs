@@ -0,0 +1,190 @@
+//! A synthetic workload: a deterministic stream of transactions, tuned by the
+//! count of clients, the mix of the transaction kinds, the share of disputes,
+//! and the pressure on the transactions cache.
+
+use std::fmt::Write;
+
+use balances::{
+    input::{Tx, TxDeposit, TxKind, TxTransfer, TxWithdrawal},
+    types::{Amount, ClientIdRepr, PositiveAmount},
+};
+
+/// The parameters of a workload.
+#[derive(Debug, Clone, Copy)]
+pub struct Workload {
+    /// The name the benchmarks report the workload under.
+    pub name: &'static str,
+
+    /// How many rows are generated.
+    pub rows: u32,
+
+    /// How many clients the transactions are spread across.
+    pub clients: u16,
+
+    /// The relative weights of deposits, withdrawals and transfers.
+    pub mix: [u32; 3],
+
+    /// The share of the deposits disputed (each dispute is followed by either
+    /// a resolve or a chargeback).
+    pub dispute_rate: f64,
+
+    /// How many transactions back the disputed deposits are.
+    pub dispute_distance: u32,
+
+    /// The size of the engine's transactions cache: the smaller, the more
+    /// disputes hit the evicted transactions.
+    pub tx_cache_size: usize,
+}
+
+/// The workloads the benchmarks are run against.
+pub const WORKLOADS: &[Workload] = &[
+    Workload {
+        name: "baseline",
+        rows: 100_000,
+        clients: 1_000,
+        mix: [6, 3, 1],
+        dispute_rate: 0.01,
+        dispute_distance: 100,
+        tx_cache_size: 1 << 20,
+    },
+    Workload {
+        name: "lru-pressure",
+        rows: 100_000,
+        clients: 1_000,
+        mix: [6, 3, 1],
+        dispute_rate: 0.05,
+        dispute_distance: 10_000,
+        tx_cache_size: 4 * 1024,
+    },
+    Workload {
+        name: "dispute-heavy",
+        rows: 100_000,
+        clients: 10,
+        mix: [1, 0, 0],
+        dispute_rate: 0.3,
+        dispute_distance: 100,
+        tx_cache_size: 1 << 20,
+    },
+];
+
+impl Workload {
+    /// The transactions of the workload.
+    pub fn transactions(&self) -> Vec<Tx> {
+        let mut rng = SplitMix64(self.rows.into());
+        let mut transactions = Vec::with_capacity(self.rows as usize);
+        let mut deposits = vec![];
+        let mut tx_id = 0u32;
+        let mix_total: u32 = self.mix.iter().sum();
+
+        while transactions.len() < self.rows as usize {
+            let client_id = (rng.next() % u64::from(self.clients)) as u16;
+            let amount = rng.amount();
+            tx_id += 1;
+
+            let roll = (rng.next() % u64::from(mix_total)) as u32;
+            let kind = if roll < self.mix[0] {
+                deposits.push((client_id, tx_id));
+                TxKind::Deposit(TxDeposit {
+                    amount_deposited: amount,
+                })
+            } else if roll < self.mix[0] + self.mix[1] {
+                TxKind::Withdrawal(TxWithdrawal {
+                    amount_withdrawn: amount,
+                })
+            } else {
+                TxKind::Transfer(TxTransfer {
+                    to_client_id: ((client_id + 1) % self.clients).into(),
+                    amount_transferred: amount,
+                })
+            };
+            transactions.push(tx(client_id, tx_id, kind));
+
+            let disputed_idx = deposits
+                .len()
+                .checked_sub(self.dispute_distance as usize + 1);
+            if let Some((client_id, tx_id)) = disputed_idx.map(|idx| deposits[idx])
+                && rng.chance(self.dispute_rate)
+            {
+                transactions.push(tx(client_id, tx_id, TxKind::Dispute));
+                let settlement = if rng.chance(0.9) {
+                    TxKind::Resolve
+                } else {
+                    TxKind::Chargeback
+                };
+                transactions.push(tx(client_id, tx_id, settlement));
+            }
+        }
+
+        transactions.truncate(self.rows as usize);
+        transactions
+    }
+
+    /// The transactions of the workload, as a CSV-file.
+    pub fn csv(&self) -> String {
+        let mut csv = String::from("type,client,tx,amount,to_client\n");
+        for tx in self.transactions() {
+            let (kind, amount_opt, to_client_id_opt) = match tx.kind {
+                TxKind::Deposit(TxDeposit { amount_deposited }) => {
+                    ("deposit", Some(amount_deposited), None)
+                }
+                TxKind::Withdrawal(TxWithdrawal { amount_withdrawn }) => {
+                    ("withdrawal", Some(amount_withdrawn), None)
+                }
+                TxKind::Transfer(TxTransfer {
+                    to_client_id,
+                    amount_transferred,
+                }) => ("transfer", Some(amount_transferred), Some(to_client_id)),
+                TxKind::Dispute => ("dispute", None, None),
+                TxKind::Resolve => ("resolve", None, None),
+                TxKind::Chargeback => ("chargeback", None, None),
+            };
+            writeln!(
+                csv,
+                "{},{},{},{},{}",
+                kind,
+                ClientIdRepr::from(tx.client_id),
+                u64::from(tx.tx_id),
+                amount_opt
+                    .map(|amount| Amount::from(amount).to_string())
+                    .unwrap_or_default(),
+                to_client_id_opt
+                    .map(|to_client_id| ClientIdRepr::from(to_client_id).to_string())
+                    .unwrap_or_default(),
+            )
+            .expect("writing into a String");
+        }
+        csv
+    }
+}
+
+fn tx(client_id: u16, tx_id: u32, kind: TxKind) -> Tx {
+    Tx {
+        client_id: client_id.into(),
+        tx_id: tx_id.into(),
+        kind,
+        timestamp: None,
+    }
+}
+
+/// A small deterministic generator, so that the runs are comparable.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn chance(&mut self, rate: f64) -> bool {
+        (self.next() as f64 / u64::MAX as f64) < rate
+    }
+
+    /// An amount of 0.0001 to 1000.0000.
+    fn amount(&mut self) -> PositiveAmount {
+        let bits = 1 + (self.next() % 10_000_000) as i128;
+        PositiveAmount::try_from(Amount::from_bits(bits)).expect("positive")
+    }
+}
@@ -1,40 +1,84 @@
 //! Processing transactions and keeping the balances.
 
-use std::collections::{HashMap, hash_map::Entry::*};
+use std::{collections::HashMap, io};
 
 use crate::{
-    input::{Tx, TxDeposit, TxKind, TxWithdrawal},
-    output::Account,
-    types::{Amount, ClientId, NonNegativeAmount, PositiveAmount, TxId},
+    input::{Tx, TxDeposit, TxKind, TxTransfer, TxWithdrawal},
+    output::{Account, Statement, Transaction, TransactionState, TxOutcome},
+    types::{Amount, ClientId, NonNegativeAmount, PositiveAmount, Timestamp, TxId},
 };
 
+pub mod accrual;
+pub mod balance_store;
+pub mod config;
 pub mod errors;
+pub mod memory;
+pub mod policy;
+mod seen_tx_ids;
+mod tx_cache;
+pub mod tx_store;
 
-use caches::{Cache, RawLRU};
+use accrual::{Accrual, Posting};
+use balance_store::BalanceStore;
 use errors::*;
-use fixnum::ops::{CheckedAdd, CheckedSub};
+use fixnum::{
+    ArithmeticError,
+    ops::{CheckedAdd, CheckedSub, Zero},
+};
+use memory::MemoryUsage;
+use policy::{DisputePolicy, LockPolicy, NegativeBalancePolicy, TimePolicy};
+use seen_tx_ids::SeenTxIds;
+use tx_cache::Transactions;
+use tx_store::TxStore;
 
-// Expected size 64M * 32B = 2GiB
-const DEFAULT_TX_LRU_SIZE: usize = 64 * 1024 * 1024;
+/// The default tx-cache size.
+// Expected size, as measured by `benches/tx_cache.rs` (the bytes allocated per
+// kept transaction, with the cache full): 64M * 114B = 7.1GiB, 7.7GiB at peak
+// (123B). With `wide-tx-ids`: 64M * 130B = 8.1GiB, 9.2GiB at peak (147B). The
+// baseline cache the slab replaced took 64M * 252B = 15.8GiB, 21.6GiB at peak
+// (346B); its former estimate of 2GiB counted the tx-ids only.
+pub const DEFAULT_TX_LRU_SIZE: usize = 64 * 1024 * 1024;
 
 /// Engine keeps balances, and changes them according to the processed
 /// transactions.
 #[derive(Debug)]
 pub struct Engine {
-    balances: HashMap<ClientId, Balance>,
-    transactions: HashMap<TxId, TxState>,
-    evictable_txs: RawLRU<TxId, ()>,
+    balances: Box<dyn BalanceStore>,
+    credit_limits: HashMap<ClientId, NonNegativeAmount>,
+    transactions: Transactions,
+    seen_tx_ids_opt: Option<SeenTxIds>,
+    charged_back_tx_ids_opt: Option<SeenTxIds>,
+    tx_store_opt: Option<Box<dyn TxStore>>,
+    memory_budget_opt: Option<usize>,
     account_pruning_enabled: bool,
+    dispute_policy: DisputePolicy,
+    lock_policy: LockPolicy,
+    negative_balance_policy: NegativeBalancePolicy,
+    time_policy: TimePolicy,
+    tx_seq_no: u64,
+    latest_timestamps: HashMap<ClientId, Timestamp>,
+    statement_period_start_opt: Option<Timestamp>,
+    statement_openings: HashMap<ClientId, Balance>,
+    statements: Vec<Statement>,
 }
 
-#[derive(Debug, Default)]
-struct Balance {
+/// The balance of an account, as kept by a [`BalanceStore`].
+#[derive(Debug, Default, Clone)]
+pub struct Balance {
     deposited: NonNegativeAmount,
     withdrawn: NonNegativeAmount,
 
+    transferred_in: NonNegativeAmount,
+    transferred_out: NonNegativeAmount,
+    transfers_reversed: NonNegativeAmount,
+
     disputed: NonNegativeAmount,
     resolved: NonNegativeAmount,
     chargedback: NonNegativeAmount,
+
+    /// Whether the account has charged back a transaction (its own deposit,
+    /// or a transfer it sent).
+    locked: bool,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -42,12 +86,24 @@ enum TxState {
     Deposited {
         amount_deposited: PositiveAmount,
         client_id: ClientId,
+        sender_opt: Option<ClientId>,
+        deposited_at: u64,
+        deposit_timestamp_opt: Option<Timestamp>,
+        dispute_count: u32,
+    },
+    Withdrawn {
+        amount_withdrawn: PositiveAmount,
+        client_id: ClientId,
     },
-    Withdrawn,
     #[allow(dead_code)] // XXX
     Disputed {
+        amount_deposited: PositiveAmount,
         amount_disputed: PositiveAmount,
         client_id: ClientId,
+        sender_opt: Option<ClientId>,
+        deposited_at: u64,
+        deposit_timestamp_opt: Option<Timestamp>,
+        dispute_count: u32,
     },
 }
 
@@ -60,74 +116,467 @@ impl Default for Engine {
 impl Engine {
     /// Create an Engine with the selected tx-cache size
     pub fn with_tx_cache_size(cache_size: usize) -> Self {
+        Self::with_balance_store(cache_size, Box::<HashMap<_, _>>::default())
+    }
+
+    /// Create an Engine with the selected tx-cache size, keeping the balances
+    /// in the selected store.
+    pub fn with_balance_store(cache_size: usize, balance_store: Box<dyn BalanceStore>) -> Self {
         Self {
-            balances: Default::default(),
-            transactions: Default::default(),
-            evictable_txs: RawLRU::new(cache_size).expect("couldn't create RawLRU"),
+            balances: balance_store,
+            credit_limits: Default::default(),
+            transactions: Transactions::new(cache_size),
+            seen_tx_ids_opt: None,
+            charged_back_tx_ids_opt: None,
+            tx_store_opt: None,
+            memory_budget_opt: None,
             account_pruning_enabled: false,
+            dispute_policy: Default::default(),
+            lock_policy: Default::default(),
+            negative_balance_policy: Default::default(),
+            time_policy: Default::default(),
+            tx_seq_no: 0,
+            latest_timestamps: Default::default(),
+            statement_period_start_opt: None,
+            statement_openings: Default::default(),
+            statements: Default::default(),
         }
     }
 
+    /// Create an Engine keeping its transactions in the cache the slab
+    /// replaced (see `benches/tx_cache.rs`).
+    #[cfg(feature = "bench")]
+    #[doc(hidden)]
+    pub fn with_baseline_tx_cache(cache_size: usize) -> Self {
+        let mut engine = Self::with_tx_cache_size(cache_size);
+        engine.transactions = Transactions::baseline(cache_size);
+        engine
+    }
+
     /// Choose whether the "empty" accounts are pruned
     pub fn set_account_pruning(&mut self, enabled: bool) {
         self.account_pruning_enabled = enabled;
     }
 
+    /// Choose whether every tx-id seen is remembered, so that the reuse of
+    /// a tx-id is detected after its transaction has been evicted from the
+    /// cache, and a tx-id charged back is reported as such (at the cost of 2
+    /// bytes per tx-id, or 8KiB per 65536 consecutive ones, whichever is
+    /// less, plus a hash-map entry per such range).
+    pub fn set_tx_id_tracking(&mut self, enabled: bool) {
+        self.seen_tx_ids_opt = enabled.then(Default::default);
+        self.charged_back_tx_ids_opt = enabled.then(Default::default);
+    }
+
+    /// Keep the transactions evicted from the cache in the store, rather than
+    /// dropping them; they are brought back when disputed.
+    pub fn set_tx_store(&mut self, tx_store: Box<dyn TxStore>) {
+        self.tx_store_opt = Some(tx_store);
+    }
+
+    /// Limit the memory (in bytes) the engine occupies: the transactions are
+    /// evicted from the cache before it is full, if the estimated
+    /// [`Engine::memory_usage`] would exceed the budget otherwise. Pair it with
+    /// [`Engine::tx_cache_size_within`] the budget.
+    pub fn set_memory_budget(&mut self, memory_budget_opt: Option<usize>) {
+        self.memory_budget_opt = memory_budget_opt;
+    }
+
+    /// The largest tx-cache size the memory budget accommodates.
+    pub fn tx_cache_size_within(memory_budget: usize) -> usize {
+        memory::tx_cache_size_within(memory_budget)
+    }
+
+    /// Choose the restrictions applied to disputes
+    pub fn set_dispute_policy(&mut self, policy: DisputePolicy) {
+        self.dispute_policy = policy;
+    }
+
+    /// Choose which transactions are permitted on the locked accounts
+    pub fn set_lock_policy(&mut self, policy: LockPolicy) {
+        self.lock_policy = policy;
+    }
+
+    /// Choose how to treat disputes holding more funds than available
+    pub fn set_negative_balance_policy(&mut self, policy: NegativeBalancePolicy) {
+        self.negative_balance_policy = policy;
+    }
+
+    /// Choose how the transactions' timestamps are validated and used
+    pub fn set_time_policy(&mut self, policy: TimePolicy) {
+        self.time_policy = policy;
+    }
+
+    /// Grant a client a credit line: their available funds may go below zero
+    /// down to the `-credit_limit`.
+    pub fn set_credit_limit(&mut self, client_id: ClientId, credit_limit: NonNegativeAmount) {
+        if Amount::from(credit_limit).signum() == 0 {
+            self.credit_limits.remove(&client_id);
+        } else {
+            self.credit_limits.insert(client_id, credit_limit);
+        }
+    }
+
+    /// The estimated memory the engine occupies.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let statements_bytes = self.statements.capacity() * size_of::<Statement>();
+        MemoryUsage {
+            balances: self.balances.memory_usage(),
+            transactions: self.transactions.memory_usage(),
+            seen_tx_ids: self
+                .seen_tx_ids_opt
+                .as_ref()
+                .map(SeenTxIds::memory_usage)
+                .unwrap_or_default()
+                + self
+                    .charged_back_tx_ids_opt
+                    .as_ref()
+                    .map(SeenTxIds::memory_usage)
+                    .unwrap_or_default(),
+            tx_store: self
+                .tx_store_opt
+                .as_ref()
+                .map(|tx_store| tx_store.memory_usage())
+                .unwrap_or_default(),
+            other: memory::hash_map_bytes::<ClientId, NonNegativeAmount>(
+                self.credit_limits.capacity(),
+            ) + memory::hash_map_bytes::<ClientId, Timestamp>(
+                self.latest_timestamps.capacity(),
+            ) + memory::hash_map_bytes::<ClientId, Balance>(
+                self.statement_openings.capacity(),
+            ) + statements_bytes,
+        }
+    }
+
     /// Iterate over all stored balances
     pub fn accounts(&self) -> impl Iterator<Item = Account> + '_ {
-        self.balances.iter().map(|(&client_id, balances)| Account {
-            client_id,
-            available: balances.available(),
-            held: balances.held(),
-            total: balances.total(),
-            is_locked: balances.is_locked(),
+        self.balances.iter().map(|(client_id, balances)| {
+            let credit_limit = self.credit_limit(client_id);
+            let available = balances.available();
+            let credit_used = Amount::ZERO
+                .saturating_sub(available)
+                .max(Amount::ZERO)
+                .min(credit_limit.into())
+                .try_into()
+                .expect("clamped between zero and a non-negative; should be non-negative");
+            let is_in_debt = available.saturating_add(credit_limit.into()).signum() < 0;
+            Account {
+                client_id,
+                available,
+                held: balances.held(),
+                total: balances.total(),
+                is_locked: balances.is_locked(),
+                credit_limit,
+                credit_used,
+                is_in_debt,
+            }
         })
     }
 
+    /// Look up a transaction that is still kept by the engine.
+    pub fn transaction(&self, tx_id: TxId) -> Option<Transaction> {
+        let transaction = match *self.transactions.get(tx_id)? {
+            TxState::Deposited {
+                amount_deposited,
+                client_id,
+                sender_opt,
+                dispute_count,
+                ..
+            } => Transaction {
+                tx_id,
+                client_id: sender_opt.unwrap_or(client_id),
+                to_client_id: sender_opt.map(|_| client_id),
+                amount: amount_deposited,
+                state: TransactionState::Deposited,
+                dispute_count,
+            },
+            TxState::Withdrawn {
+                amount_withdrawn,
+                client_id,
+            } => Transaction {
+                tx_id,
+                client_id,
+                to_client_id: None,
+                amount: amount_withdrawn,
+                state: TransactionState::Withdrawn,
+                dispute_count: 0,
+            },
+            TxState::Disputed {
+                amount_disputed,
+                client_id,
+                sender_opt,
+                dispute_count,
+                ..
+            } => Transaction {
+                tx_id,
+                client_id: sender_opt.unwrap_or(client_id),
+                to_client_id: sender_opt.map(|_| client_id),
+                amount: amount_disputed,
+                state: TransactionState::Disputed,
+                dispute_count,
+            },
+        };
+        Some(transaction)
+    }
+
+    /// Persist the balances, if the balance store is persistent.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.balances.flush()
+    }
+
+    /// Take the statements issued for the statement periods closed so far;
+    /// until taken, they are kept (and count towards the memory usage).
+    pub fn take_statements(&mut self) -> Vec<Statement> {
+        std::mem::take(&mut self.statements)
+    }
+
+    /// Close the open statement period (e.g. once the input is exhausted),
+    /// issuing a statement for every account.
+    pub fn close_statement_period(&mut self) {
+        let (Some(period), Some(period_start)) = (
+            self.time_policy.statement_period_secs,
+            self.statement_period_start_opt.take(),
+        ) else {
+            return;
+        };
+        let period_end = u64::from(period_start).saturating_add(period.get()).into();
+
+        let mut client_ids = self
+            .balances
+            .iter()
+            .map(|(client_id, _)| client_id)
+            .collect::<Vec<_>>();
+        client_ids.sort();
+
+        let openings = std::mem::take(&mut self.statement_openings);
+        for client_id in client_ids {
+            let opening = openings.get(&client_id).cloned().unwrap_or_default();
+            let statement = self
+                .balances
+                .get(client_id)
+                .expect("listed by the store")
+                .statement(client_id, &opening, period_start, period_end);
+            self.statements.push(statement);
+        }
+    }
+
+    /// Post an accrual to every account: a synthetic deposit (interest) or
+    /// withdrawal (fee) per client, numbered starting from `first_tx_id`; the
+    /// accounts in debt are skipped.
+    ///
+    /// All the amounts are calculated before anything is posted; the outcome
+    /// of processing each of the synthetic transactions is returned.
+    pub fn post_accruals(
+        &mut self,
+        accrual: &Accrual,
+        first_tx_id: TxId,
+    ) -> Result<Vec<Posting>, PostAccrualsError> {
+        let mut client_ids = self
+            .balances
+            .iter()
+            .map(|(client_id, _)| client_id)
+            .collect::<Vec<_>>();
+        client_ids.sort();
+
+        let mut postings = vec![];
+        let mut next_tx_id = Some(first_tx_id);
+        for client_id in client_ids {
+            let balance = self.balances.get(client_id).expect("listed by the store");
+            let amount = accrual.amount(balance.available(), balance.total())?;
+            let kind = match amount.signum() {
+                0 => continue,
+                1 => TxKind::Deposit(TxDeposit {
+                    amount_deposited: amount.try_into().expect("checked to be positive"),
+                }),
+                _ => TxKind::Withdrawal(TxWithdrawal {
+                    amount_withdrawn: amount
+                        .cneg()?
+                        .try_into()
+                        .expect("negation of a negative; should be positive"),
+                }),
+            };
+            let tx_id = next_tx_id.ok_or(PostAccrualsError::TxIdsExhausted)?;
+            next_tx_id = tx_id.checked_next();
+            postings.push(Tx {
+                client_id,
+                tx_id,
+                kind,
+                timestamp: None,
+            });
+        }
+
+        Ok(postings
+            .into_iter()
+            .map(|tx| {
+                let outcome = self.process_tx(tx.clone());
+                (tx, outcome)
+            })
+            .collect())
+    }
+
     /// Process a single transaction.
-    pub fn process_tx(&mut self, tx: Tx) -> Result<(), ProcessTxError> {
+    ///
+    /// A deposit, withdrawal or transfer repeating the one kept under the same
+    /// tx-id (same client, kind and amount; in the cache, or in the store) is
+    /// not applied again, and is reported as [`TxOutcome::Duplicate`].
+    pub fn process_tx(&mut self, tx: Tx) -> Result<TxOutcome, ProcessTxError> {
         let Tx {
             client_id,
             tx_id,
             kind,
+            timestamp: timestamp_opt,
         } = tx;
+        self.tx_seq_no += 1;
+
+        let carries_tx_id = matches!(
+            kind,
+            TxKind::Deposit(_) | TxKind::Withdrawal(_) | TxKind::Transfer(_)
+        );
+        if carries_tx_id {
+            // the transaction kept under the tx-id is brought back from the
+            // store, for a retry of it to be told from a reuse of the tx-id
+            self.restore(tx_id)
+                .map_err(|storage_error| -> ProcessTxError {
+                    match kind {
+                        TxKind::Deposit(_) => ProcessDepositError::from(storage_error).into(),
+                        TxKind::Withdrawal(_) => ProcessWithdrawalError::from(storage_error).into(),
+                        _ => ProcessTransferError::from(storage_error).into(),
+                    }
+                })?;
+        }
+        if self
+            .transactions
+            .get(tx_id)
+            .is_some_and(|tx_state| tx_state.is_repeated_by(client_id, &kind))
+        {
+            return Ok(TxOutcome::Duplicate);
+        }
+
+        if let Some(timestamp) = timestamp_opt {
+            self.check_timestamp(client_id, timestamp)?;
+            self.roll_statement_period(timestamp);
+        }
+
+        // a reused tx-id is reported as a duplicate, even on a locked account
+        if !self.lock_policy.allows(&kind)
+            && (!carries_tx_id || !self.is_tx_id_taken(tx_id))
+            && self.balances.get(client_id).is_some_and(Balance::is_locked)
+        {
+            let locked = AccountLocked(client_id);
+            return Err(match kind {
+                TxKind::Deposit(_) => ProcessDepositError::from(locked).into(),
+                TxKind::Withdrawal(_) => ProcessWithdrawalError::from(locked).into(),
+                TxKind::Transfer(_) => ProcessTransferError::from(locked).into(),
+                TxKind::Dispute => ProcessDisputeError::from(locked).into(),
+                TxKind::Resolve => ProcessResolveError::from(locked).into(),
+                TxKind::Chargeback => ProcessChargebackError::from(locked).into(),
+            });
+        }
+
         match kind {
-            TxKind::Deposit(deposit) => self.process_deposit(client_id, tx_id, deposit)?,
+            TxKind::Deposit(deposit) => {
+                self.process_deposit(client_id, tx_id, timestamp_opt, deposit)?
+            }
             TxKind::Withdrawal(withdrawal) => {
                 self.process_withdrawal(client_id, tx_id, withdrawal)?
             }
-            TxKind::Dispute => self.process_dispute(client_id, tx_id)?,
+            TxKind::Transfer(transfer) => {
+                self.process_transfer(client_id, tx_id, timestamp_opt, transfer)?
+            }
+            TxKind::Dispute => self.process_dispute(client_id, tx_id, timestamp_opt)?,
             TxKind::Resolve => self.process_resolve(client_id, tx_id)?,
             TxKind::Chargeback => self.process_chargeback(client_id, tx_id)?,
         }
 
-        Ok(())
+        if let Some(timestamp) = timestamp_opt {
+            let latest = self.latest_timestamps.entry(client_id).or_insert(timestamp);
+            *latest = (*latest).max(timestamp);
+        }
+        if carries_tx_id && let Some(seen_tx_ids) = self.seen_tx_ids_opt.as_mut() {
+            seen_tx_ids.insert(tx_id);
+        }
+
+        Ok(TxOutcome::Applied)
+    }
+
+    fn check_timestamp(
+        &self,
+        client_id: ClientId,
+        timestamp: Timestamp,
+    ) -> Result<(), TimestampOutOfOrder> {
+        let Some(max_lag) = self.time_policy.max_out_of_order_secs else {
+            return Ok(());
+        };
+        match self.latest_timestamps.get(&client_id) {
+            Some(&latest) if latest.secs_since(timestamp) > max_lag => {
+                Err(TimestampOutOfOrder(client_id, timestamp, latest))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Close the open statement period if the timestamp is past its end. A
+    /// transaction lagging behind is accounted in the period open at the time
+    /// it is processed.
+    fn roll_statement_period(&mut self, timestamp: Timestamp) {
+        let Some(period) = self.time_policy.statement_period_secs else {
+            return;
+        };
+        let secs = u64::from(timestamp);
+        let period_start = Timestamp::from(secs - secs % period.get());
+
+        if let Some(open_period_start) = self.statement_period_start_opt {
+            if open_period_start >= period_start {
+                return;
+            }
+            self.close_statement_period();
+        }
+        self.statement_period_start_opt = Some(period_start);
+        self.statement_openings = self
+            .balances
+            .iter()
+            .map(|(client_id, balance)| (client_id, balance.clone()))
+            .collect();
     }
 
     fn process_deposit(
         &mut self,
         client_id: ClientId,
         tx_id: TxId,
+        timestamp_opt: Option<Timestamp>,
         deposit: TxDeposit,
     ) -> Result<(), ProcessDepositError> {
         let TxDeposit { amount_deposited } = deposit;
-        let Vacant(tx) = self.transactions.entry(tx_id) else {
+        if self.is_tx_id_taken(tx_id) {
             return Err(DuplicateTxId(tx_id).into());
-        };
-        let balance = self.balances.entry(client_id).or_default();
+        }
 
-        balance.deposited = {
-            let total_deposited: Amount = balance.deposited.into();
+        let deposited = {
+            let total_deposited: Amount = self
+                .balances
+                .get(client_id)
+                .map(|balance| balance.deposited)
+                .unwrap_or_default()
+                .into();
             let amount_deposited: Amount = amount_deposited.into();
             total_deposited.cadd(amount_deposited)?.try_into().expect(
                 "sum of a non-negative and a positive, overflow handled; should be positive",
             )
         };
-        tx.insert(TxState::Deposited {
-            amount_deposited,
-            client_id,
-        });
-        self.add_to_evictable(tx_id);
+
+        self.make_room()?;
+        self.transactions.insert(
+            tx_id,
+            TxState::Deposited {
+                amount_deposited,
+                client_id,
+                sender_opt: None,
+                deposited_at: self.tx_seq_no,
+                deposit_timestamp_opt: timestamp_opt,
+                dispute_count: 0,
+            },
+        )?;
+        self.balances.get_or_insert(client_id).deposited = deposited;
 
         Ok(())
     }
@@ -139,49 +588,123 @@ impl Engine {
         withdrawal: TxWithdrawal,
     ) -> Result<(), ProcessWithdrawalError> {
         let TxWithdrawal { amount_withdrawn } = withdrawal;
-        let (mut balance, tx) = match (
-            self.balances.entry(client_id),
-            self.transactions.entry(tx_id),
-        ) {
-            (_, Occupied(_)) => return Err(DuplicateTxId(tx_id).into()),
-            (Occupied(balance), Vacant(_)) if balance.get().is_locked() => {
-                return Err(AccountLocked(client_id).into());
-            }
-
-            (Vacant(_), _) => {
-                return Err(ProcessWithdrawalError::InsufficientFunds(
-                    client_id,
-                    Default::default(),
-                ));
-            }
-            (Occupied(balance), Vacant(_))
-                if Amount::from(balance.get().available()) < Amount::from(amount_withdrawn) =>
-            {
-                return Err(ProcessWithdrawalError::InsufficientFunds(
-                    client_id,
-                    balance.get().available(),
-                ));
-            }
-
-            (Occupied(balance), Vacant(tx)) => (balance, tx),
-        };
+        if self.is_tx_id_taken(tx_id) {
+            return Err(DuplicateTxId(tx_id).into());
+        }
 
-        assert!(Amount::from(balance.get().available()) >= Amount::from(amount_withdrawn));
-        assert!(!balance.get().is_locked());
+        let spendable = spendable(&*self.balances, &self.credit_limits, client_id)?;
+        if spendable < Amount::from(amount_withdrawn) {
+            return Err(ProcessWithdrawalError::InsufficientFunds(
+                client_id, spendable,
+            ));
+        }
 
-        balance.get_mut().withdrawn = {
-            let total_withdrawn: Amount = balance.get().withdrawn.into();
+        let withdrawn = {
+            let total_withdrawn: Amount = self
+                .balances
+                .get(client_id)
+                .map(|balance| balance.withdrawn)
+                .unwrap_or_default()
+                .into();
             let amount_withdrawn: Amount = amount_withdrawn.into();
             total_withdrawn.cadd(amount_withdrawn)?.try_into().expect(
                 "sum of a non-negative and a positive, overflow handled; should be positive",
             )
         };
-        tx.insert(TxState::Withdrawn);
-        if self.account_pruning_enabled && balance.get().can_be_pruned() {
-            let _ = balance.remove();
+
+        self.make_room()?;
+        self.transactions.insert(
+            tx_id,
+            TxState::Withdrawn {
+                amount_withdrawn,
+                client_id,
+            },
+        )?;
+        self.balances.get_or_insert(client_id).withdrawn = withdrawn;
+        self.prune_if_possible(client_id);
+
+        Ok(())
+    }
+
+    fn process_transfer(
+        &mut self,
+        client_id: ClientId,
+        tx_id: TxId,
+        timestamp_opt: Option<Timestamp>,
+        transfer: TxTransfer,
+    ) -> Result<(), ProcessTransferError> {
+        let TxTransfer {
+            to_client_id,
+            amount_transferred,
+        } = transfer;
+        if self.is_tx_id_taken(tx_id) {
+            return Err(DuplicateTxId(tx_id).into());
+        }
+        if to_client_id == client_id {
+            return Err(ProcessTransferError::SelfTransfer(client_id));
+        }
+        if !self.lock_policy.allow_transfer
+            && self
+                .balances
+                .get(to_client_id)
+                .is_some_and(Balance::is_locked)
+        {
+            return Err(AccountLocked(to_client_id).into());
+        }
+
+        let spendable = spendable(&*self.balances, &self.credit_limits, client_id)?;
+        if spendable < Amount::from(amount_transferred) {
+            return Err(ProcessTransferError::InsufficientFunds(
+                client_id, spendable,
+            ));
         }
 
-        self.add_to_evictable(tx_id);
+        let amount_transferred_raw: Amount = amount_transferred.into();
+        let transferred_out = {
+            let total_transferred_out: Amount = self
+                .balances
+                .get(client_id)
+                .map(|balance| balance.transferred_out)
+                .unwrap_or_default()
+                .into();
+            total_transferred_out
+                .cadd(amount_transferred_raw)?
+                .try_into()
+                .expect(
+                    "sum of a non-negative and a positive, overflow handled; should be positive",
+                )
+        };
+        let transferred_in = {
+            let total_transferred_in: Amount = self
+                .balances
+                .get(to_client_id)
+                .map(|balance| balance.transferred_in)
+                .unwrap_or_default()
+                .into();
+            total_transferred_in
+                .cadd(amount_transferred_raw)?
+                .try_into()
+                .expect(
+                    "sum of a non-negative and a positive, overflow handled; should be positive",
+                )
+        };
+
+        self.make_room()?;
+        self.transactions.insert(
+            tx_id,
+            TxState::Deposited {
+                amount_deposited: amount_transferred,
+                client_id: to_client_id,
+                sender_opt: Some(client_id),
+                deposited_at: self.tx_seq_no,
+                deposit_timestamp_opt: timestamp_opt,
+                dispute_count: 0,
+            },
+        )?;
+        self.balances.get_or_insert(client_id).transferred_out = transferred_out;
+        self.balances.get_or_insert(to_client_id).transferred_in = transferred_in;
+        self.prune_if_possible(client_id);
+        self.prune_if_possible(to_client_id);
 
         Ok(())
     }
@@ -190,37 +713,97 @@ impl Engine {
         &mut self,
         client_id: ClientId,
         tx_id: TxId,
+        timestamp_opt: Option<Timestamp>,
     ) -> Result<(), ProcessDisputeError> {
-        let transaction = self
-            .transactions
-            .get_mut(&tx_id)
-            .ok_or(UnknownTxId(tx_id))?;
+        self.restore(tx_id)?;
+        let Some(&transaction) = self.transactions.get(tx_id) else {
+            return Err(missing_tx_id(
+                &self.charged_back_tx_ids_opt,
+                &self.seen_tx_ids_opt,
+                tx_id,
+            ));
+        };
         let TxState::Deposited {
-            amount_deposited: amount_disputed,
-            client_id: expected_client_id,
-        } = *transaction
+            amount_deposited,
+            client_id: holder_id,
+            sender_opt,
+            deposited_at,
+            deposit_timestamp_opt,
+            dispute_count,
+        } = transaction
         else {
             return Err(UnexpectedTxState.into());
         };
 
-        if client_id != expected_client_id {
+        if client_id != sender_opt.unwrap_or(holder_id) {
             return Err(UnexpectedTxState.into());
         }
 
-        let balance = self.balances.entry(client_id).or_default();
-        balance.disputed = {
-            let total_disputed: Amount = balance.disputed.into();
+        let DisputePolicy {
+            max_disputes,
+            redispute_after_resolve,
+            expire_after_txs,
+            expire_after_secs,
+        } = self.dispute_policy;
+        if max_disputes.is_some_and(|max_disputes| dispute_count >= max_disputes) {
+            return Err(DisputeLimitReached(tx_id, dispute_count).into());
+        }
+        if !redispute_after_resolve && dispute_count > 0 {
+            return Err(RedisputeForbidden(tx_id).into());
+        }
+        if expire_after_txs.is_some_and(|expire_after| self.tx_seq_no - deposited_at > expire_after)
+        {
+            return Err(DisputeExpired(tx_id).into());
+        }
+        if expire_after_secs.is_some_and(|expire_after| {
+            timestamp_opt
+                .zip(deposit_timestamp_opt)
+                .is_some_and(|(disputed_at, deposited_at)| {
+                    disputed_at.secs_since(deposited_at) > expire_after
+                })
+        }) {
+            return Err(DisputeExpired(tx_id).into());
+        }
+
+        let spendable = spendable(&*self.balances, &self.credit_limits, holder_id)?;
+        let amount_disputed = match self.negative_balance_policy {
+            NegativeBalancePolicy::Allow => amount_deposited,
+            NegativeBalancePolicy::Reject if spendable < Amount::from(amount_deposited) => {
+                return Err(ProcessDisputeError::InsufficientFunds(holder_id, spendable));
+            }
+            NegativeBalancePolicy::Reject => amount_deposited,
+            NegativeBalancePolicy::Cap => spendable
+                .min(amount_deposited.into())
+                .try_into()
+                .map_err(|_| ProcessDisputeError::InsufficientFunds(holder_id, spendable))?,
+        };
+
+        let disputed = {
+            let total_disputed: Amount = self
+                .balances
+                .get(holder_id)
+                .map(|balance| balance.disputed)
+                .unwrap_or_default()
+                .into();
             let amount_disputed: Amount = amount_disputed.into();
 
             total_disputed.cadd(amount_disputed)?.try_into().expect(
                 "sum of a non-negative and a positive, overflow handled; should be positive",
             )
         };
-        *transaction = TxState::Disputed {
-            client_id,
-            amount_disputed,
-        };
-        self.remove_from_evictable(tx_id);
+        self.transactions.insert(
+            tx_id,
+            TxState::Disputed {
+                client_id: holder_id,
+                sender_opt,
+                amount_deposited,
+                amount_disputed,
+                deposited_at,
+                deposit_timestamp_opt,
+                dispute_count: dispute_count + 1,
+            },
+        )?;
+        self.balances.get_or_insert(holder_id).disputed = disputed;
 
         Ok(())
     }
@@ -230,43 +813,59 @@ impl Engine {
         client_id: ClientId,
         tx_id: TxId,
     ) -> Result<(), ProcessResolveError> {
-        let transaction = self
-            .transactions
-            .get_mut(&tx_id)
-            .ok_or(UnknownTxId(tx_id))?;
+        let Some(transaction) = self.transactions.get(tx_id) else {
+            return Err(missing_tx_id(
+                &self.charged_back_tx_ids_opt,
+                &self.seen_tx_ids_opt,
+                tx_id,
+            ));
+        };
         let TxState::Disputed {
+            amount_deposited,
             amount_disputed,
-            client_id: expected_client_id,
+            client_id: holder_id,
+            sender_opt,
+            deposited_at,
+            deposit_timestamp_opt,
+            dispute_count,
         } = *transaction
         else {
             return Err(UnexpectedTxState.into());
         };
-        if client_id != expected_client_id {
+        if client_id != sender_opt.unwrap_or(holder_id) {
             return Err(UnexpectedTxState.into());
         }
 
-        let Occupied(mut balance) = self.balances.entry(client_id) else {
-            panic!("disputed account shouldn't have been pruned")
-        };
-
-        balance.get_mut().resolved = {
-            let total_resolved: Amount = balance.get().resolved.into();
+        let resolved = {
+            let balance = self
+                .balances
+                .get(holder_id)
+                .expect("disputed account shouldn't have been pruned");
+            let total_resolved: Amount = balance.resolved.into();
             let amount_disputed: Amount = amount_disputed.into();
 
             total_resolved.cadd(amount_disputed)?.try_into().expect(
                 "sum of a non-negative and a positive, overflow handled; should be positive",
             )
         };
-        *transaction = TxState::Deposited {
-            amount_deposited: amount_disputed,
-            client_id,
-        };
 
-        if self.account_pruning_enabled && balance.get().can_be_pruned() {
-            let _ = balance.remove();
-        }
-
-        self.add_to_evictable(tx_id);
+        self.make_room()?;
+        self.transactions.insert(
+            tx_id,
+            TxState::Deposited {
+                amount_deposited,
+                client_id: holder_id,
+                sender_opt,
+                deposited_at,
+                deposit_timestamp_opt,
+                dispute_count,
+            },
+        )?;
+        self.balances
+            .get_mut(holder_id)
+            .expect("disputed account shouldn't have been pruned")
+            .resolved = resolved;
+        self.prune_if_possible(holder_id);
 
         Ok(())
     }
@@ -276,25 +875,31 @@ impl Engine {
         client_id: ClientId,
         tx_id: TxId,
     ) -> Result<(), ProcessChargebackError> {
-        let Occupied(transaction) = self.transactions.entry(tx_id) else {
-            return Err(UnknownTxId(tx_id).into());
+        let Some(&transaction) = self.transactions.get(tx_id) else {
+            return Err(missing_tx_id(
+                &self.charged_back_tx_ids_opt,
+                &self.seen_tx_ids_opt,
+                tx_id,
+            ));
         };
         let TxState::Disputed {
             amount_disputed,
-            client_id: expected_client_id,
-        } = *transaction.get()
+            client_id: holder_id,
+            sender_opt,
+            ..
+        } = transaction
         else {
             return Err(UnexpectedTxState.into());
         };
-        if client_id != expected_client_id {
+        if client_id != sender_opt.unwrap_or(holder_id) {
             return Err(UnexpectedTxState.into());
         }
 
-        let balance = self
-            .balances
-            .get_mut(&client_id)
-            .expect("disputed account shouldn't have been pruned");
-        balance.chargedback = {
+        let chargedback = {
+            let balance = self
+                .balances
+                .get(holder_id)
+                .expect("disputed account shouldn't have been pruned");
             let total_chargedback: Amount = balance.chargedback.into();
             let amount_disputed: Amount = amount_disputed.into();
 
@@ -302,28 +907,231 @@ impl Engine {
                 "sum of a non-negative and a positive, overflow handled; should be positive",
             )
         };
-        let _ = transaction.remove();
+        // a charged back transfer is reversed: the funds return to the sender.
+        let transfers_reversed_opt = sender_opt
+            .map(|sender_id| {
+                let total_reversed: Amount = self
+                    .balances
+                    .get(sender_id)
+                    .map(|balance| balance.transfers_reversed)
+                    .unwrap_or_default()
+                    .into();
+                let amount_disputed: Amount = amount_disputed.into();
+
+                total_reversed.cadd(amount_disputed).map(|total_reversed| {
+                    let total_reversed = total_reversed.try_into().expect(
+                        "sum of a non-negative and a positive, overflow handled; should be \
+                         positive",
+                    );
+                    (sender_id, total_reversed)
+                })
+            })
+            .transpose()?;
+
+        self.balances
+            .get_mut(holder_id)
+            .expect("disputed account shouldn't have been pruned")
+            .chargedback = chargedback;
+        if let Some((sender_id, transfers_reversed)) = transfers_reversed_opt {
+            self.balances.get_or_insert(sender_id).transfers_reversed = transfers_reversed;
+        }
+        // the account which charged back is locked: the sender of a transfer,
+        // rather than its receiver, whose funds are taken back
+        self.balances.get_or_insert(client_id).locked = true;
+        let _ = self.transactions.remove(tx_id);
+        if let Some(charged_back_tx_ids) = self.charged_back_tx_ids_opt.as_mut() {
+            charged_back_tx_ids.insert(tx_id);
+        }
 
         Ok(())
     }
 
-    fn add_to_evictable(&mut self, tx_id: TxId) {
-        if let caches::PutResult::Evicted {
-            key: evicted_tx_id, ..
-        } = self.evictable_txs.put(tx_id, ())
+    fn prune_if_possible(&mut self, client_id: ClientId) {
+        if self.account_pruning_enabled
+            && self
+                .balances
+                .get(client_id)
+                .is_some_and(Balance::can_be_pruned)
         {
-            let evicted_tx_state_opt = self.transactions.remove(&evicted_tx_id);
-            assert!(matches!(
-                evicted_tx_state_opt,
-                Some(TxState::Deposited { .. } | TxState::Withdrawn)
-            ));
+            self.balances.remove(client_id);
+            self.statement_openings.remove(&client_id);
         }
     }
 
-    fn remove_from_evictable(&mut self, tx_id: TxId) {
-        self.evictable_txs
-            .remove(&tx_id)
-            .expect("should be present");
+    fn is_tx_id_taken(&self, tx_id: TxId) -> bool {
+        self.transactions.contains(tx_id)
+            || self
+                .tx_store_opt
+                .as_ref()
+                .is_some_and(|tx_store| tx_store.contains(tx_id))
+            || self
+                .seen_tx_ids_opt
+                .as_ref()
+                .is_some_and(|seen_tx_ids| seen_tx_ids.contains(tx_id))
+    }
+
+    fn credit_limit(&self, client_id: ClientId) -> NonNegativeAmount {
+        credit_limit(&self.credit_limits, client_id)
+    }
+
+    /// Evict the least recently used transactions while the cache is full,
+    /// keeping them in the store, if there is one.
+    fn make_room(&mut self) -> Result<(), StorageError> {
+        while self.is_tx_cache_full() {
+            let Some((evicted_tx_id, evicted_tx_state)) = self.transactions.peek_lru() else {
+                return Ok(());
+            };
+            if let Some(tx_store) = self.tx_store_opt.as_mut() {
+                tx_store
+                    .put(evicted_tx_id, &evicted_tx_state.encode())
+                    .map_err(StorageError)?;
+            }
+            let _ = self.transactions.pop_lru();
+        }
+
+        Ok(())
+    }
+
+    /// Whether one more transaction would not fit into the cache (or into the
+    /// memory budget).
+    fn is_tx_cache_full(&self) -> bool {
+        if self.transactions.is_full() {
+            return true;
+        }
+        let Some(memory_budget) = self.memory_budget_opt else {
+            return false;
+        };
+        // the kept transactions are accounted at the worst-case cost per
+        // entry, rather than by the capacity, which does not shrink; every
+        // other part's estimate is kept up to date as it grows, so this is
+        // O(1) per transaction
+        let memory_usage = self.memory_usage();
+        let rest_bytes = memory_usage.total() - memory_usage.transactions;
+        let tx_cache_bytes = (self.transactions.len() + 1) * tx_cache::TX_ENTRY_BYTES;
+        rest_bytes + tx_cache_bytes > memory_budget
+    }
+
+    /// Bring the transaction back from the store, if it has been evicted there.
+    fn restore(&mut self, tx_id: TxId) -> Result<(), StorageError> {
+        if self.transactions.contains(tx_id)
+            || !self
+                .tx_store_opt
+                .as_ref()
+                .is_some_and(|tx_store| tx_store.contains(tx_id))
+        {
+            return Ok(());
+        }
+
+        self.make_room()?;
+        let Some(record) = self
+            .tx_store_opt
+            .as_mut()
+            .map(|tx_store| tx_store.take(tx_id))
+            .transpose()
+            .map_err(StorageError)?
+            .flatten()
+        else {
+            return Ok(());
+        };
+        let tx_state = TxState::decode(&record).map_err(StorageError)?;
+        self.transactions.insert(tx_id, tx_state)?;
+
+        Ok(())
+    }
+}
+
+/// The error for a tx-id, no transaction is kept for.
+fn missing_tx_id<E>(
+    charged_back_tx_ids_opt: &Option<SeenTxIds>,
+    seen_tx_ids_opt: &Option<SeenTxIds>,
+    tx_id: TxId,
+) -> E
+where
+    E: From<UnknownTxId> + From<EvictedTxId> + From<ChargedBackTxId>,
+{
+    if charged_back_tx_ids_opt
+        .as_ref()
+        .is_some_and(|charged_back_tx_ids| charged_back_tx_ids.contains(tx_id))
+    {
+        ChargedBackTxId(tx_id).into()
+    } else if seen_tx_ids_opt
+        .as_ref()
+        .is_some_and(|seen_tx_ids| seen_tx_ids.contains(tx_id))
+    {
+        EvictedTxId(tx_id).into()
+    } else {
+        UnknownTxId(tx_id).into()
+    }
+}
+
+fn credit_limit(
+    credit_limits: &HashMap<ClientId, NonNegativeAmount>,
+    client_id: ClientId,
+) -> NonNegativeAmount {
+    credit_limits.get(&client_id).copied().unwrap_or_default()
+}
+
+/// The available funds, including the unused part of the credit line.
+fn spendable(
+    balances: &dyn BalanceStore,
+    credit_limits: &HashMap<ClientId, NonNegativeAmount>,
+    client_id: ClientId,
+) -> Result<Amount, ArithmeticError> {
+    let available = balances
+        .get(client_id)
+        .map(Balance::available)
+        .unwrap_or_default();
+    available.cadd(credit_limit(credit_limits, client_id).into())
+}
+
+impl TxState {
+    /// Whether the transaction described by the arguments is a repetition of
+    /// the one kept.
+    fn is_repeated_by(&self, client_id: ClientId, kind: &TxKind) -> bool {
+        match (*self, kind) {
+            (
+                TxState::Deposited {
+                    amount_deposited,
+                    client_id: holder_id,
+                    sender_opt: None,
+                    ..
+                }
+                | TxState::Disputed {
+                    amount_deposited,
+                    client_id: holder_id,
+                    sender_opt: None,
+                    ..
+                },
+                TxKind::Deposit(deposit),
+            ) => holder_id == client_id && amount_deposited == deposit.amount_deposited,
+            (
+                TxState::Withdrawn {
+                    amount_withdrawn,
+                    client_id: withdrawer_id,
+                },
+                TxKind::Withdrawal(withdrawal),
+            ) => withdrawer_id == client_id && amount_withdrawn == withdrawal.amount_withdrawn,
+            (
+                TxState::Deposited {
+                    amount_deposited,
+                    client_id: holder_id,
+                    sender_opt: Some(sender_id),
+                    ..
+                }
+                | TxState::Disputed {
+                    amount_deposited,
+                    client_id: holder_id,
+                    sender_opt: Some(sender_id),
+                    ..
+                },
+                TxKind::Transfer(transfer),
+            ) => {
+                sender_id == client_id
+                    && holder_id == transfer.to_client_id
+                    && amount_deposited == transfer.amount_transferred
+            }
+            _ => false,
+        }
     }
 }
 
@@ -331,11 +1139,17 @@ impl Balance {
     fn available(&self) -> Amount {
         let de: Amount = self.deposited.into();
         let wi: Amount = self.withdrawn.into();
+        let ti: Amount = self.transferred_in.into();
+        let to: Amount = self.transferred_out.into();
+        let tr: Amount = self.transfers_reversed.into();
         let di: Amount = self.disputed.into();
         let re: Amount = self.resolved.into();
 
         de // deposit should increase available funds
             .saturating_sub(wi) // withdrawal should decrease available funds
+            .saturating_add(ti) // incoming transfer should increase available funds
+            .saturating_sub(to) // outgoing transfer should decrease available funds
+            .saturating_add(tr) // reversed outgoing transfer returns the funds
             .saturating_sub(di) // available funds decrease by the amount disputed
             .saturating_add(re) // available funds increase by the amount resolved
     }
@@ -355,17 +1169,51 @@ impl Balance {
     fn total(&self) -> Amount {
         let de: Amount = self.deposited.into();
         let wi: Amount = self.withdrawn.into();
+        let ti: Amount = self.transferred_in.into();
+        let to: Amount = self.transferred_out.into();
+        let tr: Amount = self.transfers_reversed.into();
         let ch: Amount = self.chargedback.into();
 
         de // deposit should increase total funds
             .saturating_sub(wi) // withdrawal should decrease total funds
+            .saturating_add(ti) // incoming transfer should increase total funds
+            .saturating_sub(to) // outgoing transfer should decrease total funds
+            .saturating_add(tr) // reversed outgoing transfer returns the funds
             // total funds are unaffected by disputes
             // total funds are unaffected by resolves
             .saturating_sub(ch) // total funds decrease by the amount charged back
     }
 
     fn is_locked(&self) -> bool {
-        Amount::from(self.chargedback).signum() > 0
+        self.locked
+    }
+
+    fn statement(
+        &self,
+        client_id: ClientId,
+        opening: &Self,
+        period_start: Timestamp,
+        period_end: Timestamp,
+    ) -> Statement {
+        let since_opening = |current: NonNegativeAmount, opening: NonNegativeAmount| {
+            Amount::from(current)
+                .saturating_sub(opening.into())
+                .try_into()
+                .expect("the amounts only accrue; should be non-negative")
+        };
+        Statement {
+            client_id,
+            period_start,
+            period_end,
+            opening_total: opening.total(),
+            deposited: since_opening(self.deposited, opening.deposited),
+            withdrawn: since_opening(self.withdrawn, opening.withdrawn),
+            transferred_in: since_opening(self.transferred_in, opening.transferred_in),
+            transferred_out: since_opening(self.transferred_out, opening.transferred_out),
+            transfers_reversed: since_opening(self.transfers_reversed, opening.transfers_reversed),
+            chargedback: since_opening(self.chargedback, opening.chargedback),
+            closing_total: self.total(),
+        }
     }
 
     fn can_be_pruned(&self) -> bool {
@@ -0,0 +1,59 @@
+//! Periodic postings of interest or fees.
+
+use fixnum::{ArithmeticError, ops::Zero};
+
+use crate::{
+    engine::errors::ProcessTxError,
+    input::Tx,
+    output::TxOutcome,
+    types::{Amount, PreciseAmount, Rounding},
+};
+
+/// An interest (positive rate) or a fee (negative rate) applied to every
+/// account.
+///
+/// The postings are deposits and withdrawals like any other: an interest
+/// posted can be disputed (as a deposit), a fee can not (as a withdrawal).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Accrual {
+    /// The rate applied to the base (more precise than an [`Amount`]: only
+    /// the accrued amount is rounded).
+    pub rate: PreciseAmount,
+    /// See [`AccrualBase`].
+    pub base: AccrualBase,
+    /// How to round the accrued amount to the [`Amount`]'s precision.
+    pub rounding: Rounding,
+}
+
+/// A synthetic transaction posted for an accrual, and the outcome of its
+/// processing.
+pub type Posting = (Tx, Result<TxOutcome, ProcessTxError>);
+
+/// The funds an accrual rate is applied to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccrualBase {
+    /// The available funds.
+    Available,
+    /// The total funds.
+    Total,
+}
+
+impl Accrual {
+    /// The amount accrued to an account: the rate applied to its base; an
+    /// account whose base is negative (i.e. in debt) accrues nothing, so that
+    /// a fee never credits it.
+    pub(super) fn amount(
+        &self,
+        available: Amount,
+        total: Amount,
+    ) -> Result<Amount, ArithmeticError> {
+        let base = match self.base {
+            AccrualBase::Available => available,
+            AccrualBase::Total => total,
+        };
+        if base.signum() < 0 {
+            return Ok(Amount::ZERO);
+        }
+        self.rounding.mul(base, self.rate)
+    }
+}
@@ -0,0 +1,272 @@
+//! Keeping the balances of the accounts.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt,
+    fs::File,
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use crate::{
+    engine::{Balance, memory::hash_map_bytes},
+    types::{Amount, ClientId, ClientIdRepr, NonNegativeAmount},
+};
+
+/// Where the engine keeps the balances of the accounts.
+pub trait BalanceStore: fmt::Debug {
+    /// The balance of the client, if kept.
+    fn get(&self, client_id: ClientId) -> Option<&Balance>;
+
+    /// The balance of the client for modification, if kept.
+    fn get_mut(&mut self, client_id: ClientId) -> Option<&mut Balance>;
+
+    /// The balance of the client for modification; an empty one is kept, if
+    /// none has been.
+    fn get_or_insert(&mut self, client_id: ClientId) -> &mut Balance;
+
+    /// Stop keeping the balance of the client.
+    fn remove(&mut self, client_id: ClientId) -> Option<Balance>;
+
+    /// Iterate over all the balances kept.
+    fn iter(&self) -> Box<dyn Iterator<Item = (ClientId, &Balance)> + '_>;
+
+    /// The estimated memory (in bytes) the store occupies.
+    fn memory_usage(&self) -> usize;
+
+    /// Persist the modifications made so far, if the store is persistent.
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Keeps the balances in a vector indexed by the client-id: the fastest
+/// lookups, at the cost of a slot for every client-id up to the highest seen.
+#[derive(Debug, Default)]
+pub struct DenseBalanceStore {
+    slots: Vec<Option<Balance>>,
+}
+
+/// Keeps the balances in memory, persisting them into a file upon flush (a
+/// fixed-size record per client-id). The balances kept in the file are
+/// loaded when it is opened.
+#[derive(Debug)]
+pub struct FileBalanceStore {
+    balances: DenseBalanceStore,
+    modified: BTreeSet<ClientId>,
+    file: File,
+}
+
+impl BalanceStore for HashMap<ClientId, Balance> {
+    fn get(&self, client_id: ClientId) -> Option<&Balance> {
+        HashMap::get(self, &client_id)
+    }
+
+    fn get_mut(&mut self, client_id: ClientId) -> Option<&mut Balance> {
+        HashMap::get_mut(self, &client_id)
+    }
+
+    fn get_or_insert(&mut self, client_id: ClientId) -> &mut Balance {
+        self.entry(client_id).or_default()
+    }
+
+    fn remove(&mut self, client_id: ClientId) -> Option<Balance> {
+        HashMap::remove(self, &client_id)
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = (ClientId, &Balance)> + '_> {
+        Box::new(HashMap::iter(self).map(|(&client_id, balance)| (client_id, balance)))
+    }
+
+    fn memory_usage(&self) -> usize {
+        hash_map_bytes::<ClientId, Balance>(self.capacity())
+    }
+}
+
+impl BalanceStore for DenseBalanceStore {
+    fn get(&self, client_id: ClientId) -> Option<&Balance> {
+        self.slots.get(slot_idx(client_id))?.as_ref()
+    }
+
+    fn get_mut(&mut self, client_id: ClientId) -> Option<&mut Balance> {
+        self.slots.get_mut(slot_idx(client_id))?.as_mut()
+    }
+
+    fn get_or_insert(&mut self, client_id: ClientId) -> &mut Balance {
+        let slot_idx = slot_idx(client_id);
+        if self.slots.len() <= slot_idx {
+            self.slots.resize_with(slot_idx + 1, Default::default);
+        }
+        self.slots[slot_idx].get_or_insert_default()
+    }
+
+    fn remove(&mut self, client_id: ClientId) -> Option<Balance> {
+        self.slots.get_mut(slot_idx(client_id))?.take()
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = (ClientId, &Balance)> + '_> {
+        Box::new(
+            self.slots
+                .iter()
+                .enumerate()
+                .filter_map(|(slot_idx, slot)| {
+                    let client_id = ClientIdRepr::try_from(slot_idx)
+                        .expect("indexed by client-id")
+                        .into();
+                    slot.as_ref().map(|balance| (client_id, balance))
+                }),
+        )
+    }
+
+    fn memory_usage(&self) -> usize {
+        self.slots.capacity() * size_of::<Option<Balance>>()
+    }
+}
+
+impl FileBalanceStore {
+    /// Open (or create) the file at the path, loading the balances kept there.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut file = File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+
+        let mut balances = DenseBalanceStore::default();
+        let mut contents = vec![];
+        file.read_to_end(&mut contents)?;
+        let (records, remainder) = contents.as_chunks::<RECORD_LEN>();
+        if !remainder.is_empty() {
+            return Err(invalid_record());
+        }
+        for (slot_idx, record) in records.iter().enumerate() {
+            if let Some(balance) = Balance::decode(record)? {
+                let client_id = ClientIdRepr::try_from(slot_idx).map_err(|_| invalid_record())?;
+                *balances.get_or_insert(client_id.into()) = balance;
+            }
+        }
+
+        Ok(Self {
+            balances,
+            modified: Default::default(),
+            file,
+        })
+    }
+}
+
+impl BalanceStore for FileBalanceStore {
+    fn get(&self, client_id: ClientId) -> Option<&Balance> {
+        self.balances.get(client_id)
+    }
+
+    fn get_mut(&mut self, client_id: ClientId) -> Option<&mut Balance> {
+        self.modified.insert(client_id);
+        self.balances.get_mut(client_id)
+    }
+
+    fn get_or_insert(&mut self, client_id: ClientId) -> &mut Balance {
+        self.modified.insert(client_id);
+        self.balances.get_or_insert(client_id)
+    }
+
+    fn remove(&mut self, client_id: ClientId) -> Option<Balance> {
+        self.modified.insert(client_id);
+        self.balances.remove(client_id)
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = (ClientId, &Balance)> + '_> {
+        self.balances.iter()
+    }
+
+    fn memory_usage(&self) -> usize {
+        self.balances.memory_usage() + self.modified.len() * size_of::<ClientId>()
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        while let Some(client_id) = self.modified.pop_first() {
+            let record = Balance::encode(self.balances.get(client_id));
+            let offset = (slot_idx(client_id) * RECORD_LEN) as u64;
+            let written = self
+                .file
+                .seek(SeekFrom::Start(offset))
+                .and_then(|_| self.file.write_all(&record));
+            if let Err(reason) = written {
+                self.modified.insert(client_id);
+                return Err(reason);
+            }
+        }
+        self.file.flush()
+    }
+}
+
+fn slot_idx(client_id: ClientId) -> usize {
+    // no wider than `u32`: fits.
+    ClientIdRepr::from(client_id) as usize
+}
+
+const AMOUNT_LEN: usize = size_of::<i128>();
+const AMOUNTS_COUNT: usize = 8;
+const RECORD_LEN: usize = 1 + AMOUNTS_COUNT * AMOUNT_LEN;
+
+impl Balance {
+    fn encode(balance_opt: Option<&Self>) -> [u8; RECORD_LEN] {
+        let mut record = [0; RECORD_LEN];
+        let Some(balance) = balance_opt else {
+            return record;
+        };
+        record[0] = if balance.locked { 2 } else { 1 };
+        for (amount, chunk) in balance
+            .amounts()
+            .into_iter()
+            .zip(record[1..].chunks_exact_mut(AMOUNT_LEN))
+        {
+            chunk.copy_from_slice(&Amount::from(amount).into_bits().to_le_bytes());
+        }
+        record
+    }
+
+    fn decode(record: &[u8; RECORD_LEN]) -> io::Result<Option<Self>> {
+        let (flag, amounts) = record.split_first().expect("record is not empty");
+        let locked = match flag {
+            0 => return Ok(None),
+            1 => false,
+            2 => true,
+            _ => return Err(invalid_record()),
+        };
+        let (amounts, _) = amounts.as_chunks::<AMOUNT_LEN>();
+        let mut amounts = amounts.iter().map(|amount| {
+            NonNegativeAmount::try_from(Amount::from_bits(i128::from_le_bytes(*amount)))
+                .map_err(|_| invalid_record())
+        });
+        let mut next = || amounts.next().expect("AMOUNTS_COUNT amounts");
+        Ok(Some(Self {
+            deposited: next()?,
+            withdrawn: next()?,
+            transferred_in: next()?,
+            transferred_out: next()?,
+            transfers_reversed: next()?,
+            disputed: next()?,
+            resolved: next()?,
+            chargedback: next()?,
+            locked,
+        }))
+    }
+
+    fn amounts(&self) -> [NonNegativeAmount; AMOUNTS_COUNT] {
+        [
+            self.deposited,
+            self.withdrawn,
+            self.transferred_in,
+            self.transferred_out,
+            self.transfers_reversed,
+            self.disputed,
+            self.resolved,
+            self.chargedback,
+        ]
+    }
+}
+
+fn invalid_record() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "invalid balance record")
+}
@@ -0,0 +1,180 @@
+//! The configuration an engine is built from.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    path::PathBuf,
+};
+
+use crate::{
+    engine::{
+        DEFAULT_TX_LRU_SIZE, Engine,
+        balance_store::{BalanceStore, DenseBalanceStore, FileBalanceStore},
+        errors::{BuildEngineError, InvalidConfig, StorageError},
+        policy::{DisputePolicy, LockPolicy, NegativeBalancePolicy, TimePolicy},
+        tx_store::FileTxStore,
+    },
+    input::CreditLimit,
+};
+
+/// Everything an engine is built from. Can be loaded from (and printed as)
+/// TOML; the omitted fields take their default values.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EngineConfig {
+    /// How many undisputed transactions are kept in the cache.
+    pub tx_cache_size: usize,
+
+    /// The memory (in bytes) the engine may occupy; the tx-cache is sized down
+    /// to fit, if necessary.
+    pub memory_budget: Option<usize>,
+
+    /// Whether the "empty" accounts are pruned.
+    pub account_pruning: bool,
+
+    /// Whether every tx-id seen is remembered beyond the cache.
+    pub tx_id_tracking: bool,
+
+    /// The file the transactions evicted from the cache are kept in; `None` —
+    /// they are dropped.
+    pub tx_store_path: Option<PathBuf>,
+
+    /// See [`BalanceStoreConfig`].
+    pub balance_store: BalanceStoreConfig,
+
+    /// See [`DisputePolicy`].
+    pub dispute_policy: DisputePolicy,
+
+    /// See [`LockPolicy`].
+    pub lock_policy: LockPolicy,
+
+    /// See [`NegativeBalancePolicy`].
+    pub negative_balance_policy: NegativeBalancePolicy,
+
+    /// See [`TimePolicy`].
+    pub time_policy: TimePolicy,
+
+    /// The credit lines granted to the clients.
+    pub credit_limits: Vec<CreditLimit>,
+}
+
+/// Where the balances are kept.
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize
+)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum BalanceStoreConfig {
+    /// In a hash-map.
+    #[default]
+    HashMap,
+    /// In a vector indexed by the client-id (not with `wide-client-ids`).
+    Dense,
+    /// In memory, persisted into the file (not with `wide-client-ids`).
+    File {
+        /// The file the balances are loaded from, and saved to.
+        path: PathBuf,
+    },
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        Self {
+            tx_cache_size: DEFAULT_TX_LRU_SIZE,
+            memory_budget: None,
+            account_pruning: false,
+            tx_id_tracking: false,
+            tx_store_path: None,
+            balance_store: Default::default(),
+            dispute_policy: Default::default(),
+            lock_policy: Default::default(),
+            negative_balance_policy: Default::default(),
+            time_policy: Default::default(),
+            credit_limits: vec![],
+        }
+    }
+}
+
+impl EngineConfig {
+    /// Check that an engine can be built from this configuration.
+    pub fn validate(&self) -> Result<(), InvalidConfig> {
+        if self.tx_cache_size == 0 {
+            return Err(InvalidConfig::ZeroTxCacheSize);
+        }
+        if let Some(memory_budget) = self.memory_budget
+            && Engine::tx_cache_size_within(memory_budget) == 0
+        {
+            return Err(InvalidConfig::MemoryBudgetTooSmall(memory_budget));
+        }
+
+        if cfg!(feature = "wide-client-ids") {
+            match self.balance_store {
+                BalanceStoreConfig::HashMap => (),
+                BalanceStoreConfig::Dense => {
+                    return Err(InvalidConfig::UnboundedBalanceStore("dense"));
+                }
+                BalanceStoreConfig::File { .. } => {
+                    return Err(InvalidConfig::UnboundedBalanceStore("file"));
+                }
+            }
+        }
+
+        let mut client_ids = HashSet::new();
+        for credit_limit in &self.credit_limits {
+            if !client_ids.insert(credit_limit.client_id) {
+                return Err(InvalidConfig::DuplicateCreditLimit(credit_limit.client_id));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Build an engine, opening the stores' files.
+    pub fn build(&self) -> Result<Engine, BuildEngineError> {
+        self.validate()?;
+
+        let balance_store: Box<dyn BalanceStore> = match &self.balance_store {
+            BalanceStoreConfig::HashMap => Box::<HashMap<_, _>>::default(),
+            BalanceStoreConfig::Dense => Box::<DenseBalanceStore>::default(),
+            BalanceStoreConfig::File { path } => {
+                Box::new(FileBalanceStore::open(path).map_err(StorageError)?)
+            }
+        };
+        let tx_cache_size = self
+            .memory_budget
+            .map(Engine::tx_cache_size_within)
+            .map_or(self.tx_cache_size, |size| size.min(self.tx_cache_size));
+        let mut engine = Engine::with_balance_store(tx_cache_size, balance_store);
+        engine.set_memory_budget(self.memory_budget);
+        if let Some(tx_store_path) = &self.tx_store_path {
+            let tx_store = FileTxStore::create(tx_store_path).map_err(StorageError)?;
+            engine.set_tx_store(Box::new(tx_store));
+        }
+        engine.set_account_pruning(self.account_pruning);
+        engine.set_tx_id_tracking(self.tx_id_tracking);
+        engine.set_dispute_policy(self.dispute_policy);
+        engine.set_lock_policy(self.lock_policy);
+        engine.set_negative_balance_policy(self.negative_balance_policy);
+        engine.set_time_policy(self.time_policy);
+        for credit_limit in &self.credit_limits {
+            engine.set_credit_limit(credit_limit.client_id, credit_limit.credit_limit);
+        }
+
+        Ok(engine)
+    }
+}
+
+impl fmt::Display for EngineConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let toml = toml::to_string(self).map_err(|_| fmt::Error)?;
+        f.write_str(&toml)
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,52 @@
+use std::{fs, path::Path};
+
+use test_case::test_case;
+
+use crate::engine::config::EngineConfig;
+
+#[test_case("default")]
+#[test_case("full")]
+#[test_case("dense-balance-store")]
+#[test_case("memory-budget")]
+#[test_case("tiny-memory-budget")]
+#[test_case("zero-cache")]
+#[test_case("duplicate-credit-limit")]
+#[test_case("unknown-field")]
+fn load_config(case_name: &str) {
+    let input_file = Path::new(file!())
+        .parent()
+        .expect("file!().parent")
+        .join("cases")
+        .join(format!("{}.toml", case_name));
+    let input = fs::read_to_string(input_file).expect("fs::read_to_string");
+    let output = toml::from_str::<EngineConfig>(&input)
+        .map_err(|e| e.message().to_owned())
+        .and_then(|config| {
+            config
+                .validate()
+                .map(|()| config.to_string())
+                .map_err(|e| e.to_string())
+        })
+        .unwrap_or_else(|reason| format!("error: {}\n", reason));
+    // the dense balance stores are rejected with the wide client-ids.
+    let snapshot_name = if cfg!(feature = "wide-client-ids")
+        && ["full", "dense-balance-store"].contains(&case_name)
+    {
+        format!("{}.wide-client-ids", case_name)
+    } else {
+        case_name.to_owned()
+    };
+    insta::with_settings!({
+        snapshot_path => "cases",
+        prepend_module_to_snapshot => false,
+    }, {
+        insta::assert_snapshot!(snapshot_name, output);
+    });
+}
+
+#[test]
+fn printed_config_is_loaded_back() {
+    let config = EngineConfig::default();
+    let loaded = toml::from_str::<EngineConfig>(&config.to_string()).expect("toml::from_str");
+    assert_eq!(loaded, config);
+}
@@ -1,8 +1,10 @@
 //! Error types
 
+use std::io;
+
 use fixnum::ArithmeticError;
 
-use crate::types::{Amount, ClientId, TxId};
+use crate::types::{Amount, ClientId, Timestamp, TxId};
 
 /// An error processing a transaction of any supported kind.
 #[derive(Debug, thiserror::Error)]
@@ -23,6 +25,14 @@ pub enum ProcessTxError {
         ProcessWithdrawalError,
     ),
 
+    /// See [`ProcessTransferError`]
+    #[error("{}", _0)]
+    Transfer(
+        #[from]
+        #[source]
+        ProcessTransferError,
+    ),
+
     /// See [`ProcessDisputeError`]
     #[error("{}", _0)]
     Dispute(
@@ -46,6 +56,14 @@ pub enum ProcessTxError {
         #[source]
         ProcessChargebackError,
     ),
+
+    /// See [`TimestampOutOfOrder`]
+    #[error("{}", _0)]
+    TimestampOutOfOrder(
+        #[from]
+        #[source]
+        TimestampOutOfOrder,
+    ),
 }
 
 /// An error processing deposit-transaction
@@ -66,6 +84,22 @@ pub enum ProcessDepositError {
         #[source]
         ArithmeticError,
     ),
+
+    /// See [`AccountLocked`]
+    #[error("{}", _0)]
+    AccountLocked(
+        #[from]
+        #[source]
+        AccountLocked,
+    ),
+
+    /// See [`StorageError`]
+    #[error("{}", _0)]
+    Storage(
+        #[from]
+        #[source]
+        StorageError,
+    ),
 }
 
 /// An error processing withdrawal-transaction
@@ -99,6 +133,59 @@ pub enum ProcessWithdrawalError {
     /// requested withdrwal.
     #[error("Insufficient funds: {} has {}", _0, _1)]
     InsufficientFunds(ClientId, Amount),
+
+    /// See [`StorageError`]
+    #[error("{}", _0)]
+    Storage(
+        #[from]
+        #[source]
+        StorageError,
+    ),
+}
+
+/// An error processing transfer-transaction
+#[derive(Debug, thiserror::Error)]
+pub enum ProcessTransferError {
+    /// See [`DuplicateTxId`]
+    #[error("{}", _0)]
+    DuplicateTxId(
+        #[from]
+        #[source]
+        DuplicateTxId,
+    ),
+
+    /// An arithmetic error during the balance calculation.
+    #[error("Arithmetic error: {}", _0)]
+    Overflow(
+        #[from]
+        #[source]
+        ArithmeticError,
+    ),
+
+    /// See [`AccountLocked`]
+    #[error("{}", _0)]
+    AccountLocked(
+        #[from]
+        #[source]
+        AccountLocked,
+    ),
+
+    /// The sender does not have enough available funds to complete the
+    /// requested transfer.
+    #[error("Insufficient funds: {} has {}", _0, _1)]
+    InsufficientFunds(ClientId, Amount),
+
+    /// The sender and the receiver are the same client.
+    #[error("transfer to self: {}", _0)]
+    SelfTransfer(ClientId),
+
+    /// See [`StorageError`]
+    #[error("{}", _0)]
+    Storage(
+        #[from]
+        #[source]
+        StorageError,
+    ),
 }
 
 /// An error processing dispute-transaction
@@ -112,6 +199,22 @@ pub enum ProcessDisputeError {
         UnknownTxId,
     ),
 
+    /// See [`EvictedTxId`]
+    #[error("{}", _0)]
+    EvictedTxId(
+        #[from]
+        #[source]
+        EvictedTxId,
+    ),
+
+    /// See [`ChargedBackTxId`]
+    #[error("{}", _0)]
+    ChargedBackTxId(
+        #[from]
+        #[source]
+        ChargedBackTxId,
+    ),
+
     /// See [`UnexpectedTxState`]
     #[error("{}", _0)]
     UnexpectedTxState(
@@ -120,6 +223,30 @@ pub enum ProcessDisputeError {
         UnexpectedTxState,
     ),
 
+    /// See [`DisputeLimitReached`]
+    #[error("{}", _0)]
+    DisputeLimitReached(
+        #[from]
+        #[source]
+        DisputeLimitReached,
+    ),
+
+    /// See [`RedisputeForbidden`]
+    #[error("{}", _0)]
+    RedisputeForbidden(
+        #[from]
+        #[source]
+        RedisputeForbidden,
+    ),
+
+    /// See [`DisputeExpired`]
+    #[error("{}", _0)]
+    DisputeExpired(
+        #[from]
+        #[source]
+        DisputeExpired,
+    ),
+
     /// An arithmetic error during the balance calculation.
     #[error("Arithmetic error: {}", _0)]
     Overflow(
@@ -127,6 +254,27 @@ pub enum ProcessDisputeError {
         #[source]
         ArithmeticError,
     ),
+
+    /// See [`AccountLocked`]
+    #[error("{}", _0)]
+    AccountLocked(
+        #[from]
+        #[source]
+        AccountLocked,
+    ),
+
+    /// The client does not have enough available funds to hold the disputed
+    /// amount.
+    #[error("Insufficient funds: {} has {}", _0, _1)]
+    InsufficientFunds(ClientId, Amount),
+
+    /// See [`StorageError`]
+    #[error("{}", _0)]
+    Storage(
+        #[from]
+        #[source]
+        StorageError,
+    ),
 }
 
 /// An error processing resolve-transaction
@@ -140,6 +288,22 @@ pub enum ProcessResolveError {
         UnknownTxId,
     ),
 
+    /// See [`EvictedTxId`]
+    #[error("{}", _0)]
+    EvictedTxId(
+        #[from]
+        #[source]
+        EvictedTxId,
+    ),
+
+    /// See [`ChargedBackTxId`]
+    #[error("{}", _0)]
+    ChargedBackTxId(
+        #[from]
+        #[source]
+        ChargedBackTxId,
+    ),
+
     /// See [`UnexpectedTxState`]
     #[error("{}", _0)]
     UnexpectedTxState(
@@ -155,6 +319,22 @@ pub enum ProcessResolveError {
         #[source]
         ArithmeticError,
     ),
+
+    /// See [`AccountLocked`]
+    #[error("{}", _0)]
+    AccountLocked(
+        #[from]
+        #[source]
+        AccountLocked,
+    ),
+
+    /// See [`StorageError`]
+    #[error("{}", _0)]
+    Storage(
+        #[from]
+        #[source]
+        StorageError,
+    ),
 }
 
 /// An error processing chargeback-transaction
@@ -168,6 +348,22 @@ pub enum ProcessChargebackError {
         UnknownTxId,
     ),
 
+    /// See [`EvictedTxId`]
+    #[error("{}", _0)]
+    EvictedTxId(
+        #[from]
+        #[source]
+        EvictedTxId,
+    ),
+
+    /// See [`ChargedBackTxId`]
+    #[error("{}", _0)]
+    ChargedBackTxId(
+        #[from]
+        #[source]
+        ChargedBackTxId,
+    ),
+
     /// See [`UnexpectedTxState`]
     #[error("{}", _0)]
     UnexpectedTxState(
@@ -183,6 +379,75 @@ pub enum ProcessChargebackError {
         #[source]
         ArithmeticError,
     ),
+
+    /// See [`AccountLocked`]
+    #[error("{}", _0)]
+    AccountLocked(
+        #[from]
+        #[source]
+        AccountLocked,
+    ),
+}
+
+/// An error posting accruals. No accruals are posted if it occurs.
+#[derive(Debug, thiserror::Error)]
+pub enum PostAccrualsError {
+    /// An arithmetic error during the accrual calculation.
+    #[error("Arithmetic error: {}", _0)]
+    Overflow(
+        #[from]
+        #[source]
+        ArithmeticError,
+    ),
+
+    /// There are not enough tx-ids left after the first one to post an
+    /// accrual to every account.
+    #[error("tx-ids exhausted")]
+    TxIdsExhausted,
+}
+
+/// An error building an engine from its configuration.
+#[derive(Debug, thiserror::Error)]
+pub enum BuildEngineError {
+    /// See [`InvalidConfig`]
+    #[error("{}", _0)]
+    InvalidConfig(
+        #[from]
+        #[source]
+        InvalidConfig,
+    ),
+
+    /// See [`StorageError`]
+    #[error("{}", _0)]
+    Storage(
+        #[from]
+        #[source]
+        StorageError,
+    ),
+}
+
+/// The configuration is inconsistent.
+#[derive(Debug, thiserror::Error)]
+pub enum InvalidConfig {
+    /// The transactions cache cannot be empty.
+    #[error("invalid config: tx_cache_size must be positive")]
+    ZeroTxCacheSize,
+
+    /// The memory budget does not accommodate a single transaction.
+    #[error("invalid config: memory budget of {} bytes is too small", _0)]
+    MemoryBudgetTooSmall(usize),
+
+    /// More than one credit line is granted to a client.
+    #[error("invalid config: duplicate credit limit for {}", _0)]
+    DuplicateCreditLimit(ClientId),
+
+    /// The balance store keeps a slot for every client-id up to the highest
+    /// seen: unbounded with the `wide-client-ids`.
+    #[error(
+        "invalid config: the `{}` balance store is not supported with wide client-ids",
+        _0
+    )]
+    UnboundedBalanceStore(&'static str),
 }
 
 /// Transaction was rejected due to having a non-unique tx-id.
@@ -195,6 +460,24 @@ pub struct DuplicateTxId(pub TxId);
 #[error("unknown tx-id: {}", _0)]
 pub struct UnknownTxId(pub TxId);
 
+/// The transaction with the specified tx-id has been seen, but is no longer
+/// kept (evicted from the cache).
+#[derive(Debug, thiserror::Error)]
+#[error("evicted tx-id: {}", _0)]
+pub struct EvictedTxId(pub TxId);
+
+/// The transaction with the specified tx-id has been charged back, and is no
+/// longer kept.
+#[derive(Debug, thiserror::Error)]
+#[error("charged-back tx-id: {}", _0)]
+pub struct ChargedBackTxId(pub TxId);
+
+/// The transactions store failed to keep or bring back a transaction (or the
+/// cache has no slot left for one).
+#[derive(Debug, thiserror::Error)]
+#[error("storage error: {}", _0)]
+pub struct StorageError(#[source] pub io::Error);
+
 /// The refered transaction's state is incompatible with the requested
 /// operation.
 #[derive(Debug, thiserror::Error)]
@@ -205,3 +488,27 @@ pub struct UnexpectedTxState;
 #[derive(Debug, thiserror::Error)]
 #[error("account locked: {}", _0)]
 pub struct AccountLocked(pub ClientId);
+
+/// The transaction has already been disputed as many times as the dispute
+/// policy allows.
+#[derive(Debug, thiserror::Error)]
+#[error("dispute limit reached: {} disputed {} times", _0, _1)]
+pub struct DisputeLimitReached(pub TxId, pub u32);
+
+/// The dispute policy forbids disputing a transaction whose previous dispute
+/// has been resolved.
+#[derive(Debug, thiserror::Error)]
+#[error("re-dispute forbidden: {}", _0)]
+pub struct RedisputeForbidden(pub TxId);
+
+/// Too many transactions have been processed, or too much time has passed,
+/// since the disputed one.
+#[derive(Debug, thiserror::Error)]
+#[error("dispute expired: {}", _0)]
+pub struct DisputeExpired(pub TxId);
+
+/// The transaction's timestamp lags too far behind the latest one processed
+/// for the client.
+#[derive(Debug, thiserror::Error)]
+#[error("timestamp out of order: {} at {} after {}", _0, _1, _2)]
+pub struct TimestampOutOfOrder(pub ClientId, pub Timestamp, pub Timestamp);
@@ -0,0 +1,60 @@
+//! Estimating the memory the engine occupies.
+
+use std::mem::size_of;
+
+use crate::engine::tx_cache::TX_ENTRY_BYTES;
+
+/// The estimated memory (in bytes) occupied by the engine's parts. The
+/// allocator's own overhead is not accounted for.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub struct MemoryUsage {
+    /// The balances of the accounts.
+    pub balances: usize,
+
+    /// The cached transactions, with their LRU-order (at the current
+    /// capacity).
+    pub transactions: usize,
+
+    /// The sets of the tx-ids seen, and of the charged back ones, if tracked.
+    pub seen_tx_ids: usize,
+
+    /// The memory-resident part of the store of the evicted transactions.
+    pub tx_store: usize,
+
+    /// The credit limits, the latest timestamps, and the statements.
+    pub other: usize,
+}
+
+impl MemoryUsage {
+    /// The estimate for the engine as a whole.
+    pub fn total(&self) -> usize {
+        self.balances + self.transactions + self.seen_tx_ids + self.tx_store + self.other
+    }
+}
+
+/// The bytes a hash-map of the given capacity (as reported by
+/// `HashMap::capacity`) occupies: a bucket and a control byte per slot, the
+/// slots count being a power of two kept at most 7/8 full.
+pub(crate) fn hash_map_bytes<K, V>(capacity: usize) -> usize {
+    let buckets = match capacity {
+        0 => return 0,
+        1..8 => (capacity + 1).next_power_of_two(),
+        _ => (capacity * 8 / 7).next_power_of_two(),
+    };
+    buckets * (size_of::<(K, V)>() + 1) + GROUP_WIDTH
+}
+
+/// The bytes a hash-map occupies per entry at worst: with the entries removed
+/// and inserted, it doubles once more than half of its capacity is in use, so
+/// only 7/32 of its slots may be occupied.
+pub(crate) const fn hash_map_entry_bytes<K, V>() -> usize {
+    (size_of::<(K, V)>() + 1) * 32 / 7
+}
+
+/// The tx-cache size that fits within the memory budget, leaving an eighth of
+/// it for the rest of the engine.
+pub(crate) fn tx_cache_size_within(memory_budget: usize) -> usize {
+    (memory_budget - memory_budget / 8) / TX_ENTRY_BYTES
+}
+
+const GROUP_WIDTH: usize = 16;
@@ -0,0 +1,147 @@
+//! Policies tuning how the engine treats the transactions.
+
+use std::num::NonZeroU64;
+
+use crate::input::TxKind;
+
+/// Restrictions on the dispute lifecycle of a transaction.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize
+)]
+#[serde(default, deny_unknown_fields)]
+pub struct DisputePolicy {
+    /// How many times a single transaction may be disputed; `None` — no limit.
+    pub max_disputes: Option<u32>,
+
+    /// Whether a transaction may be disputed again once its previous dispute
+    /// has been resolved.
+    pub redispute_after_resolve: bool,
+
+    /// How many subsequent transactions (the dispute itself included) may be
+    /// processed before a deposit can no longer be disputed; `None` — never
+    /// expires.
+    pub expire_after_txs: Option<u64>,
+
+    /// How many seconds may pass between a deposit and its dispute; `None` —
+    /// never expires. Only applies if both of them carry a timestamp.
+    pub expire_after_secs: Option<u64>,
+}
+
+impl Default for DisputePolicy {
+    fn default() -> Self {
+        Self {
+            max_disputes: None,
+            redispute_after_resolve: true,
+            expire_after_txs: None,
+            expire_after_secs: None,
+        }
+    }
+}
+
+/// Transaction kinds that are permitted on a locked account.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize
+)]
+#[serde(default, deny_unknown_fields)]
+pub struct LockPolicy {
+    /// Whether funds can be deposited into a locked account.
+    pub allow_deposit: bool,
+    /// Whether funds can be withdrawn from a locked account.
+    pub allow_withdrawal: bool,
+    /// Whether funds can be transferred from or to a locked account.
+    pub allow_transfer: bool,
+    /// Whether deposits into a locked account can be disputed.
+    pub allow_dispute: bool,
+    /// Whether disputes on a locked account can be resolved.
+    pub allow_resolve: bool,
+    /// Whether disputes on a locked account can be charged back.
+    pub allow_chargeback: bool,
+}
+
+impl Default for LockPolicy {
+    fn default() -> Self {
+        Self {
+            allow_deposit: true,
+            allow_withdrawal: false,
+            allow_transfer: false,
+            allow_dispute: true,
+            allow_resolve: true,
+            allow_chargeback: true,
+        }
+    }
+}
+
+impl LockPolicy {
+    /// Whether a transaction of this kind is permitted on a locked account.
+    pub fn allows(&self, kind: &TxKind) -> bool {
+        match kind {
+            TxKind::Deposit(_) => self.allow_deposit,
+            TxKind::Withdrawal(_) => self.allow_withdrawal,
+            TxKind::Transfer(_) => self.allow_transfer,
+            TxKind::Dispute => self.allow_dispute,
+            TxKind::Resolve => self.allow_resolve,
+            TxKind::Chargeback => self.allow_chargeback,
+        }
+    }
+}
+
+/// What to do with a dispute holding more funds than the client has available
+/// (e.g. the disputed deposit has already been withdrawn).
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize
+)]
+#[serde(rename_all = "snake_case")]
+pub enum NegativeBalancePolicy {
+    /// Hold the whole disputed amount; the account may end up in debt.
+    #[default]
+    Allow,
+    /// Reject the dispute.
+    Reject,
+    /// Hold no more than the client has available.
+    Cap,
+}
+
+/// How the engine treats the transactions' timestamps. Transactions without a
+/// timestamp are exempt.
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize
+)]
+#[serde(default, deny_unknown_fields)]
+pub struct TimePolicy {
+    /// How many seconds a client's transaction may lag behind the latest one
+    /// processed for that client (`Some(0)` — timestamps must not decrease);
+    /// `None` — the order is not validated.
+    pub max_out_of_order_secs: Option<u64>,
+
+    /// The length of a statement period in seconds, the periods being aligned
+    /// to the Unix epoch; `None` — no statements are issued. The statements
+    /// are kept until taken (see
+    /// [`Engine::take_statements`](crate::engine::Engine::take_statements)).
+    pub statement_period_secs: Option<NonZeroU64>,
+}
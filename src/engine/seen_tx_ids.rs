@@ -0,0 +1,106 @@
+//! Remembering every tx-id ever seen, beyond the transactions cache.
+
+use std::collections::HashMap;
+
+use crate::{engine::memory::hash_map_bytes, types::TxId};
+
+const PAGE_BITS: u64 = 1 << 16;
+const PAGE_WORDS: usize = (PAGE_BITS / u64::BITS as u64) as usize;
+
+/// The count of the tx-ids a sparse page holds at most: as many bytes as a
+/// dense one occupies.
+const SPARSE_PAGE_LEN: usize = size_of::<[u64; PAGE_WORDS]>() / size_of::<u16>();
+
+/// An exact set of tx-ids, in pages of 65536 consecutive tx-ids each: a page
+/// is a sorted list of the tx-ids seen in its range (2 bytes per tx-id), and
+/// turns into an 8KiB bitmap once the list would be as large.
+#[derive(Debug, Default)]
+pub(crate) struct SeenTxIds {
+    pages: HashMap<u64, Page>,
+    /// The bytes the pages occupy, kept up to date as they grow (so that the
+    /// memory usage is known without walking them).
+    pages_bytes: usize,
+}
+
+#[derive(Debug)]
+enum Page {
+    Sparse(Vec<u16>),
+    Dense(Box<[u64; PAGE_WORDS]>),
+}
+
+impl SeenTxIds {
+    pub(crate) fn insert(&mut self, tx_id: TxId) {
+        let (page_no, bit_idx) = locate(tx_id);
+        let page = self
+            .pages
+            .entry(page_no)
+            .or_insert_with(|| Page::Sparse(vec![]));
+        match page {
+            Page::Sparse(bit_idxs) => {
+                let Err(pos) = bit_idxs.binary_search(&bit_idx) else {
+                    return;
+                };
+                if bit_idxs.len() < SPARSE_PAGE_LEN {
+                    let capacity = bit_idxs.capacity();
+                    bit_idxs.insert(pos, bit_idx);
+                    self.pages_bytes += (bit_idxs.capacity() - capacity) * size_of::<u16>();
+                    return;
+                }
+                let mut words = Box::new([0; PAGE_WORDS]);
+                for &bit_idx in bit_idxs.iter().chain([&bit_idx]) {
+                    let (word_idx, bit) = locate_bit(bit_idx);
+                    words[word_idx] |= bit;
+                }
+                self.pages_bytes -= bit_idxs.capacity() * size_of::<u16>();
+                self.pages_bytes += size_of::<[u64; PAGE_WORDS]>();
+                *page = Page::Dense(words);
+            }
+            Page::Dense(words) => {
+                let (word_idx, bit) = locate_bit(bit_idx);
+                words[word_idx] |= bit;
+            }
+        }
+    }
+
+    pub(crate) fn contains(&self, tx_id: TxId) -> bool {
+        let (page_no, bit_idx) = locate(tx_id);
+        match self.pages.get(&page_no) {
+            None => false,
+            Some(Page::Sparse(bit_idxs)) => bit_idxs.binary_search(&bit_idx).is_ok(),
+            Some(Page::Dense(words)) => {
+                let (word_idx, bit) = locate_bit(bit_idx);
+                words[word_idx] & bit != 0
+            }
+        }
+    }
+
+    pub(crate) fn memory_usage(&self) -> usize {
+        hash_map_bytes::<u64, Page>(self.pages.capacity()) + self.pages_bytes
+    }
+
+    /// The memory usage, with the pages' bytes counted by walking them.
+    #[cfg(test)]
+    pub(crate) fn walked_memory_usage(&self) -> usize {
+        let pages_bytes = self
+            .pages
+            .values()
+            .map(|page| match page {
+                Page::Sparse(bit_idxs) => bit_idxs.capacity() * size_of::<u16>(),
+                Page::Dense(_) => size_of::<[u64; PAGE_WORDS]>(),
+            })
+            .sum::<usize>();
+        hash_map_bytes::<u64, Page>(self.pages.capacity()) + pages_bytes
+    }
+}
+
+fn locate(tx_id: TxId) -> (u64, u16) {
+    let tx_id = u64::from(tx_id);
+    let bit_idx = u16::try_from(tx_id % PAGE_BITS).expect("less than PAGE_BITS");
+    (tx_id / PAGE_BITS, bit_idx)
+}
+
+fn locate_bit(bit_idx: u16) -> (usize, u64) {
+    let word_idx = usize::from(bit_idx) / u64::BITS as usize;
+    let bit = 1 << (u32::from(bit_idx) % u64::BITS);
+    (word_idx, bit)
+}
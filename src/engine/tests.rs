@@ -1,8 +1,24 @@
-use std::collections::BTreeMap;
+use std::{
+    collections::{BTreeMap, HashMap},
+    num::NonZeroU64,
+    path::PathBuf,
+};
 
 use test_case::test_case;
 
-use crate::{engine::Engine, input::Tx};
+use crate::{
+    engine::{
+        Engine,
+        accrual::{Accrual, AccrualBase},
+        balance_store::{BalanceStore, DenseBalanceStore, FileBalanceStore},
+        policy::{DisputePolicy, LockPolicy, NegativeBalancePolicy, TimePolicy},
+        seen_tx_ids::SeenTxIds,
+        tx_store::{FileTxStore, MemoryTxStore, TxStore},
+    },
+    input::Tx,
+    output::{Account, TxOutcome},
+    types::{Amount, ClientId, NonNegativeAmount, PreciseAmount, Rounding},
+};
 
 #[test_case([]; "baseline")]
 #[test_case([
@@ -85,14 +101,518 @@ use crate::{engine::Engine, input::Tx};
     t::cb(1, 1),
     t::w(1, 3, "1.0"),
 ]; "case-16")]
+#[test_case([
+    t::d(1, 1, "2.0"),
+    t::tr(1, 2, 2, "1.5"),
+    t::tr(2, 1, 3, "2.0"),
+    t::tr(1, 1, 4, "0.5"),
+    t::tr(1, 2, 2, "0.5"),
+]; "case-17")]
+#[test_case([
+    t::d(1, 1, "1.0"),
+    t::tr(1, 2, 2, "1.0"),
+    t::di(2, 2),
+    t::di(1, 2),
+    t::re(1, 2),
+]; "case-18")]
+#[test_case([
+    t::d(1, 1, "1.0"),
+    t::tr(1, 2, 2, "1.0"),
+    t::di(1, 2),
+    t::cb(1, 2),
+]; "case-19")]
+#[test_case([
+    t::d(1, 1, "1.0"),
+    t::di(1, 1),
+    t::cb(1, 1),
+    t::d(2, 2, "1.0"),
+    t::tr(2, 1, 3, "1.0"),
+    t::tr(1, 2, 4, "1.0"),
+]; "case-20")]
+#[test_case([
+    t::d(1, 1, "1.0"),
+    t::d(1, 1, "1.0"),
+    t::d(1, 1, "2.0"),
+    t::d(2, 1, "1.0"),
+    t::w(1, 2, "0.5"),
+    t::w(1, 2, "0.5"),
+    t::d(1, 2, "0.5"),
+    t::tr(1, 2, 3, "0.1"),
+    t::tr(1, 2, 3, "0.1"),
+    t::tr(2, 1, 3, "0.1"),
+    t::di(1, 1),
+    t::d(1, 1, "1.0"),
+]; "case-21")]
 fn process_transactions(transactions: impl IntoIterator<Item = Tx>) {
+    let mut engine = Engine::default();
+    let transcript = replay(&mut engine, transactions);
+
+    assert_case_snapshot((transcript, balances(&engine)));
+}
+
+#[test_case(DisputePolicy::default(), [
+    t::d(1, 1, "1.0"),
+    t::di(1, 1),
+    t::re(1, 1),
+    t::di(1, 1),
+    t::re(1, 1),
+    t::di(1, 1),
+]; "unrestricted")]
+#[test_case(DisputePolicy { max_disputes: Some(2), ..Default::default() }, [
+    t::d(1, 1, "1.0"),
+    t::di(1, 1),
+    t::re(1, 1),
+    t::di(1, 1),
+    t::re(1, 1),
+    t::di(1, 1),
+]; "max-disputes")]
+#[test_case(DisputePolicy { redispute_after_resolve: false, ..Default::default() }, [
+    t::d(1, 1, "1.0"),
+    t::di(1, 1),
+    t::re(1, 1),
+    t::di(1, 1),
+]; "no-redispute")]
+#[test_case(DisputePolicy { expire_after_txs: Some(2), ..Default::default() }, [
+    t::d(1, 1, "1.0"),
+    t::d(1, 2, "1.0"),
+    t::di(1, 2),
+    t::di(1, 1),
+]; "expiry")]
+fn process_transactions_with_dispute_policy(
+    dispute_policy: DisputePolicy,
+    transactions: impl IntoIterator<Item = Tx>,
+) {
+    let mut engine = Engine::default();
+    engine.set_dispute_policy(dispute_policy);
+    let transcript = replay(&mut engine, transactions);
+
+    assert_case_snapshot((
+        transcript,
+        engine
+            .transactions
+            .tx_ids()
+            .map(|tx_id| (tx_id, engine.transaction(tx_id)))
+            .collect::<BTreeMap<_, _>>(),
+    ));
+}
+
+#[test_case(false; "untracked")]
+#[test_case(true; "tracked")]
+fn process_transactions_with_tx_id_tracking(tx_id_tracking: bool) {
+    let mut engine = Engine::with_tx_cache_size(2);
+    engine.set_tx_id_tracking(tx_id_tracking);
+    let transcript = replay(
+        &mut engine,
+        [
+            t::d(1, 1, "1.0"),
+            t::d(1, 2, "1.0"),
+            t::w(1, 3, "1.0"),
+            t::d(1, 1, "1.0"),
+            t::di(1, 1),
+            t::di(1, 2),
+            t::di(1, 4),
+            t::d(1, 4, "1.0"),
+            t::di(1, 4),
+            t::cb(1, 4),
+            t::re(1, 4),
+            t::di(1, 4),
+        ],
+    );
+
+    assert_case_snapshot((transcript, accounts(&engine)));
+}
+
+#[test_case(None; "dropped")]
+#[test_case(Some(Box::new(MemoryTxStore::default())); "memory")]
+#[test_case(Some(Box::new(FileTxStore::create(temp_file("tx-store")).unwrap())); "file")]
+fn process_transactions_with_tx_store(tx_store_opt: Option<Box<dyn TxStore>>) {
+    let mut engine = Engine::with_tx_cache_size(2);
+    if let Some(tx_store) = tx_store_opt {
+        engine.set_tx_store(tx_store);
+    }
+    let transcript = replay(
+        &mut engine,
+        [
+            t::d(1, 1, "1.0"),
+            t::tr(1, 2, 2, "0.5"),
+            t::w(2, 3, "0.5"),
+            t::di(1, 1),
+            t::d(1, 2, "0.5"),
+            t::di(1, 2),
+            t::re(1, 2),
+            t::cb(1, 1),
+            t::w(2, 3, "0.5"),
+            t::tr(1, 2, 2, "0.5"),
+            t::d(1, 4, "1.0"),
+            t::w(2, 3, "0.5"),
+        ],
+    );
+
+    assert_case_snapshot((transcript, accounts(&engine)));
+}
+
+#[test_case(Box::<HashMap<_, _>>::default(); "hashmap")]
+#[test_case(Box::<DenseBalanceStore>::default(); "dense")]
+#[test_case(Box::new(FileBalanceStore::open(temp_file("balance-store")).unwrap()); "file")]
+fn process_transactions_with_balance_store(balance_store: Box<dyn BalanceStore>) {
+    let mut engine = Engine::with_balance_store(16, balance_store);
+    let transcript = replay(
+        &mut engine,
+        [
+            t::d(3, 1, "1.0"),
+            t::d(1, 2, "2.0"),
+            t::tr(1, 2, 3, "0.5"),
+            t::w(2, 4, "0.5"),
+            t::di(3, 1),
+            t::cb(3, 1),
+        ],
+    );
+    engine.flush().unwrap();
+
+    assert_case_snapshot((transcript, accounts(&engine)));
+}
+
+#[test]
+fn file_balance_store_reopened() {
+    let path = temp_file("balance-store-reopened");
+
+    let mut engine =
+        Engine::with_balance_store(16, Box::new(FileBalanceStore::open(&path).unwrap()));
+    let _ = replay(
+        &mut engine,
+        [
+            t::d(3, 1, "1.0"),
+            t::d(1, 2, "2.0"),
+            t::w(1, 3, "2.0"),
+            t::di(3, 1),
+            t::d(2, 4, "1.0"),
+            t::tr(2, 1, 5, "0.5"),
+            t::di(2, 5),
+            t::cb(2, 5),
+        ],
+    );
+    engine.flush().unwrap();
+    let engine = Engine::with_balance_store(16, Box::new(FileBalanceStore::open(&path).unwrap()));
+
+    assert_case_snapshot(accounts(&engine));
+}
+
+#[test]
+fn memory_budget_respected() {
+    const MEMORY_BUDGET: usize = 512 * 1024;
+
+    let mut engine = Engine::with_tx_cache_size(Engine::tx_cache_size_within(MEMORY_BUDGET));
+    engine.set_memory_budget(Some(MEMORY_BUDGET));
+    engine.set_tx_id_tracking(true);
+
+    // the tx-ids span 20 pages of the tracking set, squeezing the cache
+    for seq_no in 0..20_000 {
+        let tx_id = ((seq_no % 20) << 16) | (seq_no / 20);
+        let outcome = engine.process_tx(t::d((seq_no % 50) as u16, tx_id, "1.0"));
+        assert!(matches!(outcome, Ok(TxOutcome::Applied)));
+        assert!(engine.memory_usage().total() <= MEMORY_BUDGET);
+    }
+    assert!(engine.memory_usage().seen_tx_ids > 20_000 * 2);
+
+    let oldest = engine.process_tx(t::di(0, 0));
+    assert_eq!(oldest.unwrap_err().to_string(), "evicted tx-id: T:0");
+    let latest = engine.process_tx(t::di(49, (19 << 16) | 999));
+    assert!(matches!(latest, Ok(TxOutcome::Applied)));
+}
+
+/// A page of the tracked tx-ids stays a sorted list (2 bytes per tx-id) until
+/// it would be as large as a bitmap; the tx-ids far apart cost no bitmap.
+#[test]
+fn seen_tx_ids_pages() {
+    let mut seen_tx_ids = SeenTxIds::default();
+    for tx_id in (0..4096u32).map(|seq_no| seq_no * 16) {
+        seen_tx_ids.insert(tx_id.into());
+        seen_tx_ids.insert(tx_id.into());
+    }
+    let sparse_bytes = seen_tx_ids.memory_usage();
+    assert!(sparse_bytes <= 8 * 1024 + 1024, "{}", sparse_bytes);
+
+    seen_tx_ids.insert(1u32.into());
+    assert!(seen_tx_ids.memory_usage() <= sparse_bytes + 1024);
+    for seq_no in 0..4096u32 {
+        assert!(seen_tx_ids.contains((seq_no * 16).into()));
+        assert!(!seen_tx_ids.contains((seq_no * 16 + 2).into()));
+    }
+    assert!(seen_tx_ids.contains(1u32.into()));
+    assert_eq!(
+        seen_tx_ids.memory_usage(),
+        seen_tx_ids.walked_memory_usage()
+    );
+
+    let mut sparse = SeenTxIds::default();
+    for page_no in 0..1000u32 {
+        sparse.insert((page_no << 16).into());
+    }
+    assert!(
+        sparse.memory_usage() < 1000 * 256,
+        "{}",
+        sparse.memory_usage()
+    );
+    assert_eq!(sparse.memory_usage(), sparse.walked_memory_usage());
+    assert!(sparse.contains((999u32 << 16).into()));
+    assert!(!sparse.contains(((999u32 << 16) + 1).into()));
+}
+
+#[test_case(LockPolicy::default(), [
+    t::d(1, 1, "1.0"),
+    t::d(1, 2, "1.0"),
+    t::di(1, 1),
+    t::cb(1, 1),
+    t::d(1, 3, "1.0"),
+    t::di(1, 2),
+    t::re(1, 2),
+    t::w(1, 4, "1.0"),
+]; "default")]
+#[test_case(LockPolicy { allow_deposit: false, allow_dispute: false, ..Default::default() }, [
+    t::d(1, 1, "1.0"),
+    t::d(1, 2, "1.0"),
+    t::di(1, 1),
+    t::cb(1, 1),
+    t::d(1, 3, "1.0"),
+    t::di(1, 2),
+]; "deny-deposit-and-dispute")]
+#[test_case(LockPolicy { allow_withdrawal: true, allow_resolve: false, ..Default::default() }, [
+    t::d(1, 1, "1.0"),
+    t::d(1, 2, "1.0"),
+    t::di(1, 1),
+    t::cb(1, 1),
+    t::di(1, 2),
+    t::re(1, 2),
+    t::cb(1, 2),
+    t::d(1, 3, "1.0"),
+    t::w(1, 4, "1.0"),
+]; "allow-withdrawal-deny-resolve")]
+#[test_case(LockPolicy::default(), [
+    t::d(1, 1, "1.0"),
+    t::d(1, 2, "1.0"),
+    t::di(1, 1),
+    t::cb(1, 1),
+    t::w(1, 2, "0.5"),
+    t::tr(1, 2, 2, "0.5"),
+    t::w(1, 3, "0.5"),
+]; "reused-tx-id-on-locked")]
+fn process_transactions_with_lock_policy(
+    lock_policy: LockPolicy,
+    transactions: impl IntoIterator<Item = Tx>,
+) {
+    let mut engine = Engine::default();
+    engine.set_lock_policy(lock_policy);
+    let transcript = replay(&mut engine, transactions);
+
+    assert_case_snapshot((transcript, balances(&engine)));
+}
+
+#[test_case([(1, "1.0")], [
+    t::d(1, 1, "1.0"),
+    t::w(1, 2, "1.5"),
+    t::w(1, 3, "0.5"),
+    t::w(1, 4, "0.0001"),
+]; "credit-drawn")]
+#[test_case([(1, "1.0")], [
+    t::w(1, 1, "0.5"),
+    t::d(1, 2, "1.0"),
+]; "credit-repaid")]
+#[test_case([(1, "1.0"), (2, "0.0")], [
+    t::w(1, 1, "1.0"),
+    t::w(2, 2, "1.0"),
+]; "no-balance")]
+fn process_transactions_with_credit_limits(
+    credit_limits: impl IntoIterator<Item = (u16, &'static str)>,
+    transactions: impl IntoIterator<Item = Tx>,
+) {
+    let mut engine = Engine::default();
+    for (client_id, credit_limit) in credit_limits {
+        let credit_limit =
+            NonNegativeAmount::try_from(Amount::from_str_exact(credit_limit).unwrap()).unwrap();
+        engine.set_credit_limit(client_id.into(), credit_limit);
+    }
+    let transcript = replay(&mut engine, transactions);
+
+    assert_case_snapshot((transcript, accounts(&engine)));
+}
+
+#[test_case(NegativeBalancePolicy::Allow, [
+    t::d(1, 1, "1.0"),
+    t::w(1, 2, "0.4"),
+    t::di(1, 1),
+]; "allow")]
+#[test_case(NegativeBalancePolicy::Reject, [
+    t::d(1, 1, "1.0"),
+    t::w(1, 2, "0.4"),
+    t::di(1, 1),
+    t::d(1, 3, "0.4"),
+    t::di(1, 1),
+]; "reject")]
+#[test_case(NegativeBalancePolicy::Cap, [
+    t::d(1, 1, "1.0"),
+    t::w(1, 2, "0.4"),
+    t::di(1, 1),
+    t::re(1, 1),
+    t::w(1, 3, "0.6"),
+    t::di(1, 1),
+]; "cap-resolve")]
+#[test_case(NegativeBalancePolicy::Cap, [
+    t::d(1, 1, "1.0"),
+    t::w(1, 2, "0.4"),
+    t::di(1, 1),
+    t::cb(1, 1),
+]; "cap-chargeback")]
+fn process_transactions_with_negative_balance_policy(
+    negative_balance_policy: NegativeBalancePolicy,
+    transactions: impl IntoIterator<Item = Tx>,
+) {
+    let mut engine = Engine::default();
+    engine.set_negative_balance_policy(negative_balance_policy);
+    let transcript = replay(&mut engine, transactions);
+
+    assert_case_snapshot((transcript, accounts(&engine)));
+}
+
+#[test_case("0.5", AccrualBase::Available, Rounding::HalfEven; "half-even")]
+#[test_case("0.5", AccrualBase::Available, Rounding::HalfUp; "half-up")]
+#[test_case("0.5", AccrualBase::Available, Rounding::Truncate; "truncate")]
+#[test_case("-0.5", AccrualBase::Total, Rounding::HalfEven; "fee-on-total")]
+#[test_case("0.00015", AccrualBase::Available, Rounding::HalfEven; "precise-rate")]
+fn post_accruals(rate: &str, base: AccrualBase, rounding: Rounding) {
+    let mut engine = Engine::default();
+    engine.set_credit_limit(
+        5u16.into(),
+        NonNegativeAmount::try_from(Amount::from_str_exact("2.0").unwrap()).unwrap(),
+    );
+    let transcript = replay(
+        &mut engine,
+        [
+            t::d(1, 1, "1.0001"),
+            t::d(2, 2, "1.0003"),
+            t::d(3, 3, "1.0"),
+            t::di(3, 3),
+            t::d(4, 4, "1.0"),
+            t::w(4, 5, "1.0"),
+            t::w(5, 6, "1.0"),
+        ],
+    );
+    let accrual = Accrual {
+        rate: PreciseAmount::from_str_exact(rate).unwrap(),
+        base,
+        rounding,
+    };
+    let postings = engine
+        .post_accruals(&accrual, 100u32.into())
+        .unwrap()
+        .into_iter()
+        .map(|(tx, outcome)| (format!("{:?}", tx), outcome.map_err(|e| e.to_string())))
+        .collect::<Vec<_>>();
+    // an interest is disputed like any deposit; a fee, like any withdrawal, is not
+    let dispute = engine.process_tx(t::di(1, 100)).map_err(|e| e.to_string());
+
+    assert_case_snapshot((transcript, postings, dispute, accounts(&engine)));
+}
+
+#[test_case(TimePolicy { max_out_of_order_secs: Some(0), ..Default::default() }, DisputePolicy::default(), [
+    t::at(10, t::d(1, 1, "1.0")),
+    t::at(10, t::d(1, 2, "1.0")),
+    t::at(9, t::w(1, 3, "1.0")),
+    t::at(5, t::d(2, 4, "1.0")),
+    t::w(1, 5, "1.0"),
+]; "monotonic")]
+#[test_case(TimePolicy { max_out_of_order_secs: Some(5), ..Default::default() }, DisputePolicy::default(), [
+    t::at(10, t::d(1, 1, "1.0")),
+    t::at(5, t::d(1, 2, "1.0")),
+    t::at(4, t::d(1, 3, "1.0")),
+    t::at(8, t::d(1, 4, "1.0")),
+]; "bounded-skew")]
+#[test_case(TimePolicy::default(), DisputePolicy { expire_after_secs: Some(100), ..Default::default() }, [
+    t::at(0, t::d(1, 1, "1.0")),
+    t::at(50, t::d(1, 2, "1.0")),
+    t::d(1, 3, "1.0"),
+    t::at(100, t::di(1, 1)),
+    t::at(151, t::di(1, 2)),
+    t::at(1000, t::di(1, 3)),
+    t::di(1, 1),
+]; "dispute-window")]
+#[test_case(TimePolicy { statement_period_secs: NonZeroU64::new(100), ..Default::default() }, DisputePolicy::default(), [
+    t::at(10, t::d(1, 1, "3.0")),
+    t::at(20, t::tr(1, 2, 2, "1.0")),
+    t::at(120, t::w(1, 3, "1.0")),
+    t::at(130, t::di(1, 1)),
+    t::at(90, t::d(2, 4, "1.0")),
+    t::at(350, t::cb(1, 1)),
+    t::at(360, t::d(1, 5, "1.0")),
+]; "statements")]
+fn process_transactions_with_time_policy(
+    time_policy: TimePolicy,
+    dispute_policy: DisputePolicy,
+    transactions: impl IntoIterator<Item = Tx>,
+) {
+    let mut engine = Engine::default();
+    engine.set_time_policy(time_policy);
+    engine.set_dispute_policy(dispute_policy);
+    let transcript = replay(&mut engine, transactions);
+    engine.close_statement_period();
+
+    assert_case_snapshot((transcript, engine.take_statements()));
+}
+
+/// Snapshot the case (named after the test) into `cases`.
+fn assert_case_snapshot(snapshot: impl serde::Serialize) {
     let case_name = std::thread::current()
         .name()
         .unwrap()
         .to_owned()
         .replace("::", "-");
 
-    let mut engine = Engine::default();
+    insta::with_settings!({
+        snapshot_path => "cases",
+        prepend_module_to_snapshot => false,
+    }, {
+        insta::assert_yaml_snapshot!(case_name, snapshot);
+    });
+}
+
+/// The accounts, by the client-id.
+fn accounts(engine: &Engine) -> BTreeMap<ClientId, Account> {
+    engine
+        .accounts()
+        .map(|account| (account.client_id, account))
+        .collect()
+}
+
+/// The available, held and total funds of every account, and whether it is
+/// locked.
+fn balances(engine: &Engine) -> BTreeMap<ClientId, (Amount, NonNegativeAmount, Amount, bool)> {
+    engine
+        .balances
+        .iter()
+        .map(|(client_id, balance)| {
+            (
+                client_id,
+                (
+                    balance.available(),
+                    balance.held(),
+                    balance.total(),
+                    balance.is_locked(),
+                ),
+            )
+        })
+        .collect()
+}
+
+/// A path for a test's file, with no file there.
+fn temp_file(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("balances-{}-{}", std::process::id(), name));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+fn replay(
+    engine: &mut Engine,
+    transactions: impl IntoIterator<Item = Tx>,
+) -> Vec<(String, Result<TxOutcome, String>)> {
     let mut transcript = vec![];
 
     for tx in transactions {
@@ -100,26 +620,12 @@ fn process_transactions(transactions: impl IntoIterator<Item = Tx>) {
         transcript.push((format!("{:?}", tx), outcome.map_err(|e| e.to_string())));
     }
 
-    insta::with_settings!({
-        snapshot_path => "cases",
-        prepend_module_to_snapshot => false,
-    }, {
-        insta::assert_yaml_snapshot!(case_name,
-            (
-                transcript,
-                engine.balances.into_iter()
-                    .map(|(client_id, balance)|
-                        (client_id, (balance.available(), balance.held(), balance.total(), balance.is_locked()))
-                    )
-                    .collect::<BTreeMap<_,_>>(),
-            ),
-        );
-    });
+    transcript
 }
 
 mod t {
     use crate::{
-        input::{Tx, TxDeposit, TxKind, TxWithdrawal},
+        input::{Tx, TxDeposit, TxKind, TxTransfer, TxWithdrawal},
         types::{Amount, PositiveAmount},
     };
 
@@ -132,6 +638,7 @@ mod t {
             client_id,
             tx_id,
             kind: TxKind::Deposit(TxDeposit { amount_deposited }),
+            timestamp: None,
         }
     }
 
@@ -144,6 +651,36 @@ mod t {
             client_id,
             tx_id,
             kind: TxKind::Withdrawal(TxWithdrawal { amount_withdrawn }),
+            timestamp: None,
+        }
+    }
+
+    pub(super) fn tr(
+        client_id: u16,
+        to_client_id: u16,
+        tx_id: u32,
+        amount_transferred: &str,
+    ) -> Tx {
+        let client_id = client_id.into();
+        let to_client_id = to_client_id.into();
+        let tx_id = tx_id.into();
+        let amount_transferred =
+            PositiveAmount::try_from(Amount::from_str_exact(amount_transferred).unwrap()).unwrap();
+        Tx {
+            client_id,
+            tx_id,
+            kind: TxKind::Transfer(TxTransfer {
+                to_client_id,
+                amount_transferred,
+            }),
+            timestamp: None,
+        }
+    }
+
+    pub(super) fn at(timestamp: u64, tx: Tx) -> Tx {
+        Tx {
+            timestamp: Some(timestamp.into()),
+            ..tx
         }
     }
 
@@ -154,6 +691,7 @@ mod t {
             client_id,
             tx_id,
             kind: TxKind::Dispute,
+            timestamp: None,
         }
     }
 
@@ -164,6 +702,7 @@ mod t {
             client_id,
             tx_id,
             kind: TxKind::Resolve,
+            timestamp: None,
         }
     }
 
@@ -174,6 +713,7 @@ mod t {
             client_id,
             tx_id,
             kind: TxKind::Chargeback,
+            timestamp: None,
         }
     }
 }
@@ -0,0 +1,223 @@
+//! Keeping the transactions, and the LRU-order of the evictable ones.
+
+use std::{collections::HashMap, io};
+
+use crate::{
+    engine::{
+        TxState,
+        errors::StorageError,
+        memory::{hash_map_bytes, hash_map_entry_bytes},
+    },
+    types::TxId,
+};
+
+#[cfg(feature = "bench")]
+mod baseline;
+
+/// The transactions cache of the engine: the slab, or (with the `bench`
+/// feature) either the slab or the baseline it replaced.
+#[cfg(not(feature = "bench"))]
+pub(crate) type Transactions = TxCache;
+#[cfg(feature = "bench")]
+pub(crate) use baseline::Transactions;
+
+/// The transactions, kept in a slab indexed by the tx-id. The evictable ones
+/// (i.e. not disputed) are also linked into a list, from the least recently
+/// used to the most recently used one; the links are the slots' indices.
+#[derive(Debug)]
+pub(crate) struct TxCache {
+    cache_size: usize,
+    index: HashMap<TxId, u32>,
+    slots: Vec<Slot>,
+    free_head: u32,
+    lru: u32,
+    mru: u32,
+    evictable_len: usize,
+}
+
+#[derive(Debug)]
+struct Slot {
+    tx_id: TxId,
+    tx_state: TxState,
+    prev: u32,
+    next: u32,
+}
+
+/// The end of a list.
+const NIL: u32 = u32::MAX;
+
+/// The bytes a kept transaction costs at worst: its entry in the index, and
+/// its slot (in a vector, up to twice as large as needed).
+pub(crate) const TX_ENTRY_BYTES: usize =
+    hash_map_entry_bytes::<TxId, u32>() + 2 * size_of::<Slot>();
+
+impl TxCache {
+    /// No more than `cache_size` evictable transactions are to be kept.
+    pub(crate) fn new(cache_size: usize) -> Self {
+        Self {
+            cache_size,
+            index: Default::default(),
+            slots: Default::default(),
+            free_head: NIL,
+            lru: NIL,
+            mru: NIL,
+            evictable_len: 0,
+        }
+    }
+
+    /// The count of the transactions kept.
+    pub(crate) fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    /// Whether the evictable transactions fill the cache.
+    pub(crate) fn is_full(&self) -> bool {
+        self.evictable_len >= self.cache_size
+    }
+
+    pub(crate) fn contains(&self, tx_id: TxId) -> bool {
+        self.index.contains_key(&tx_id)
+    }
+
+    pub(crate) fn get(&self, tx_id: TxId) -> Option<&TxState> {
+        let &slot_idx = self.index.get(&tx_id)?;
+        Some(&self.slot(slot_idx).tx_state)
+    }
+
+    /// Keep the transaction under the tx-id, replacing the one kept, if any.
+    /// If evictable, it becomes the most recently used one. Fails (keeping
+    /// nothing) if a new transaction finds no slot left.
+    pub(crate) fn insert(&mut self, tx_id: TxId, tx_state: TxState) -> Result<(), StorageError> {
+        let slot_idx = match self.index.get(&tx_id) {
+            Some(&slot_idx) => {
+                self.unlink(slot_idx);
+                self.slot_mut(slot_idx).tx_state = tx_state;
+                slot_idx
+            }
+            None => {
+                let slot_idx = self.allocate(tx_id, tx_state)?;
+                self.index.insert(tx_id, slot_idx);
+                slot_idx
+            }
+        };
+        if tx_state.is_evictable() {
+            self.link_as_mru(slot_idx);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn remove(&mut self, tx_id: TxId) -> Option<TxState> {
+        let slot_idx = self.index.remove(&tx_id)?;
+        self.unlink(slot_idx);
+        let free_head = self.free_head;
+        let slot = self.slot_mut(slot_idx);
+        slot.next = free_head;
+        let tx_state = slot.tx_state;
+        self.free_head = slot_idx;
+        Some(tx_state)
+    }
+
+    /// The least recently used evictable transaction.
+    pub(crate) fn peek_lru(&self) -> Option<(TxId, &TxState)> {
+        let slot = self.slots.get(self.lru as usize)?;
+        Some((slot.tx_id, &slot.tx_state))
+    }
+
+    /// Stop keeping the least recently used evictable transaction.
+    pub(crate) fn pop_lru(&mut self) -> Option<(TxId, TxState)> {
+        let tx_id = self.slots.get(self.lru as usize)?.tx_id;
+        let tx_state = self.remove(tx_id)?;
+        Some((tx_id, tx_state))
+    }
+
+    /// The estimated memory (in bytes) occupied.
+    pub(crate) fn memory_usage(&self) -> usize {
+        hash_map_bytes::<TxId, u32>(self.index.capacity())
+            + self.slots.capacity() * size_of::<Slot>()
+    }
+
+    #[cfg(test)]
+    pub(crate) fn tx_ids(&self) -> impl Iterator<Item = TxId> + '_ {
+        self.index.keys().copied()
+    }
+
+    fn allocate(&mut self, tx_id: TxId, tx_state: TxState) -> Result<u32, StorageError> {
+        let slot = Slot {
+            tx_id,
+            tx_state,
+            prev: NIL,
+            next: NIL,
+        };
+        if self.free_head != NIL {
+            let slot_idx = self.free_head;
+            self.free_head = self.slot(slot_idx).next;
+            *self.slot_mut(slot_idx) = slot;
+            return Ok(slot_idx);
+        }
+        let slot_idx = u32::try_from(self.slots.len())
+            .ok()
+            .filter(|&slot_idx| slot_idx != NIL)
+            .ok_or_else(|| {
+                StorageError(io::Error::new(
+                    io::ErrorKind::StorageFull,
+                    "more than u32::MAX-1 transactions kept",
+                ))
+            })?;
+        self.slots.push(slot);
+        Ok(slot_idx)
+    }
+
+    fn link_as_mru(&mut self, slot_idx: u32) {
+        let mru = self.mru;
+        let slot = self.slot_mut(slot_idx);
+        slot.prev = mru;
+        slot.next = NIL;
+        match mru {
+            NIL => self.lru = slot_idx,
+            mru => self.slot_mut(mru).next = slot_idx,
+        }
+        self.mru = slot_idx;
+        self.evictable_len += 1;
+    }
+
+    /// Unlink the slot from the list, if it is linked (i.e. evictable).
+    fn unlink(&mut self, slot_idx: u32) {
+        let Slot {
+            prev,
+            next,
+            tx_state,
+            ..
+        } = *self.slot(slot_idx);
+        if !tx_state.is_evictable() {
+            return;
+        }
+        match prev {
+            NIL => self.lru = next,
+            prev => self.slot_mut(prev).next = next,
+        }
+        match next {
+            NIL => self.mru = prev,
+            next => self.slot_mut(next).prev = prev,
+        }
+        let slot = self.slot_mut(slot_idx);
+        slot.prev = NIL;
+        slot.next = NIL;
+        self.evictable_len -= 1;
+    }
+
+    fn slot(&self, slot_idx: u32) -> &Slot {
+        &self.slots[slot_idx as usize]
+    }
+
+    fn slot_mut(&mut self, slot_idx: u32) -> &mut Slot {
+        &mut self.slots[slot_idx as usize]
+    }
+}
+
+impl TxState {
+    /// Whether the transaction may be evicted from the cache (i.e. not
+    /// disputed).
+    fn is_evictable(&self) -> bool {
+        !matches!(self, TxState::Disputed { .. })
+    }
+}
@@ -0,0 +1,147 @@
+//! The transactions cache the slab replaced, kept for the benchmark comparing
+//! the two (see `benches/tx_cache.rs`).
+
+use std::{collections::HashMap, fmt};
+
+use caches::{Cache, RawLRU};
+
+use crate::{
+    engine::{TxState, errors::StorageError, memory::hash_map_bytes, tx_cache::TxCache},
+    types::TxId,
+};
+
+/// The transactions cache of an engine built with the `bench` feature.
+#[derive(Debug)]
+pub(crate) enum Transactions {
+    Slab(TxCache),
+    Baseline(BaselineTxCache),
+}
+
+/// Every transaction kept in a hash-map, and the tx-id of every evictable
+/// one in an LRU (whose hash-map is allocated for the whole cache size
+/// upfront).
+pub(crate) struct BaselineTxCache {
+    transactions: HashMap<TxId, TxState>,
+    evictable_txs: RawLRU<TxId, ()>,
+}
+
+/// The bytes of a node of the LRU-list (the key, and the links).
+const LRU_NODE_BYTES: usize =
+    (size_of::<TxId>() + 2 * size_of::<usize>()).next_multiple_of(align_of::<usize>());
+
+impl Transactions {
+    pub(crate) fn new(cache_size: usize) -> Self {
+        Self::Slab(TxCache::new(cache_size))
+    }
+
+    pub(crate) fn baseline(cache_size: usize) -> Self {
+        Self::Baseline(BaselineTxCache {
+            transactions: Default::default(),
+            evictable_txs: RawLRU::new(cache_size).expect("couldn't create RawLRU"),
+        })
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        match self {
+            Self::Slab(tx_cache) => tx_cache.len(),
+            Self::Baseline(tx_cache) => tx_cache.transactions.len(),
+        }
+    }
+
+    pub(crate) fn is_full(&self) -> bool {
+        match self {
+            Self::Slab(tx_cache) => tx_cache.is_full(),
+            Self::Baseline(tx_cache) => {
+                tx_cache.evictable_txs.len() >= tx_cache.evictable_txs.cap()
+            }
+        }
+    }
+
+    pub(crate) fn contains(&self, tx_id: TxId) -> bool {
+        match self {
+            Self::Slab(tx_cache) => tx_cache.contains(tx_id),
+            Self::Baseline(tx_cache) => tx_cache.transactions.contains_key(&tx_id),
+        }
+    }
+
+    pub(crate) fn get(&self, tx_id: TxId) -> Option<&TxState> {
+        match self {
+            Self::Slab(tx_cache) => tx_cache.get(tx_id),
+            Self::Baseline(tx_cache) => tx_cache.transactions.get(&tx_id),
+        }
+    }
+
+    pub(crate) fn insert(&mut self, tx_id: TxId, tx_state: TxState) -> Result<(), StorageError> {
+        let tx_cache = match self {
+            Self::Slab(tx_cache) => return tx_cache.insert(tx_id, tx_state),
+            Self::Baseline(tx_cache) => tx_cache,
+        };
+        let replaced_opt = tx_cache.transactions.insert(tx_id, tx_state);
+        if replaced_opt.is_some_and(|replaced| replaced.is_evictable()) {
+            let _ = tx_cache.evictable_txs.remove(&tx_id);
+        }
+        if tx_state.is_evictable() {
+            let _ = tx_cache.evictable_txs.put(tx_id, ());
+        }
+        Ok(())
+    }
+
+    pub(crate) fn remove(&mut self, tx_id: TxId) -> Option<TxState> {
+        let tx_cache = match self {
+            Self::Slab(tx_cache) => return tx_cache.remove(tx_id),
+            Self::Baseline(tx_cache) => tx_cache,
+        };
+        let tx_state = tx_cache.transactions.remove(&tx_id)?;
+        if tx_state.is_evictable() {
+            let _ = tx_cache.evictable_txs.remove(&tx_id);
+        }
+        Some(tx_state)
+    }
+
+    pub(crate) fn peek_lru(&self) -> Option<(TxId, &TxState)> {
+        match self {
+            Self::Slab(tx_cache) => tx_cache.peek_lru(),
+            Self::Baseline(tx_cache) => {
+                let (&tx_id, _) = tx_cache.evictable_txs.peek_lru()?;
+                Some((tx_id, tx_cache.transactions.get(&tx_id)?))
+            }
+        }
+    }
+
+    pub(crate) fn pop_lru(&mut self) -> Option<(TxId, TxState)> {
+        let tx_cache = match self {
+            Self::Slab(tx_cache) => return tx_cache.pop_lru(),
+            Self::Baseline(tx_cache) => tx_cache,
+        };
+        let (tx_id, ()) = tx_cache.evictable_txs.remove_lru()?;
+        let tx_state = tx_cache.transactions.remove(&tx_id)?;
+        Some((tx_id, tx_state))
+    }
+
+    pub(crate) fn memory_usage(&self) -> usize {
+        match self {
+            Self::Slab(tx_cache) => tx_cache.memory_usage(),
+            Self::Baseline(tx_cache) => {
+                hash_map_bytes::<TxId, TxState>(tx_cache.transactions.capacity())
+                    + hash_map_bytes::<[usize; 2], ()>(tx_cache.evictable_txs.cap())
+                    + tx_cache.evictable_txs.len() * LRU_NODE_BYTES
+            }
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn tx_ids(&self) -> Box<dyn Iterator<Item = TxId> + '_> {
+        match self {
+            Self::Slab(tx_cache) => Box::new(tx_cache.tx_ids()),
+            Self::Baseline(tx_cache) => Box::new(tx_cache.transactions.keys().copied()),
+        }
+    }
+}
+
+impl fmt::Debug for BaselineTxCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BaselineTxCache")
+            .field("transactions", &self.transactions)
+            .finish_non_exhaustive()
+    }
+}
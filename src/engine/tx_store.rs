@@ -0,0 +1,237 @@
+//! Keeping the transactions evicted from the engine's cache.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::File,
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use crate::{
+    engine::{TxState, memory::hash_map_bytes},
+    types::{Amount, ClientId, ClientIdRepr, PositiveAmount, Timestamp, TxId},
+};
+
+/// Where the transactions evicted from the engine's cache are kept, to be
+/// brought back when disputed. The transactions are passed encoded.
+pub trait TxStore: fmt::Debug {
+    /// Keep the transaction under the tx-id.
+    fn put(&mut self, tx_id: TxId, record: &[u8]) -> io::Result<()>;
+
+    /// Whether a transaction is kept under the tx-id.
+    fn contains(&self, tx_id: TxId) -> bool;
+
+    /// Stop keeping the transaction under the tx-id, and return it.
+    fn take(&mut self, tx_id: TxId) -> io::Result<Option<Vec<u8>>>;
+
+    /// The estimated memory (in bytes) the store occupies.
+    fn memory_usage(&self) -> usize;
+}
+
+/// Keeps the evicted transactions in memory, in their encoded (more compact)
+/// form.
+#[derive(Debug, Default)]
+pub struct MemoryTxStore {
+    records: HashMap<TxId, Vec<u8>>,
+    records_bytes: usize,
+}
+
+/// Appends the evicted transactions to a file, keeping only their offsets in
+/// memory. The space of the transactions brought back is not reclaimed.
+#[derive(Debug)]
+pub struct FileTxStore {
+    file: File,
+    file_len: u64,
+    offsets: HashMap<TxId, (u64, u8)>,
+}
+
+impl TxStore for MemoryTxStore {
+    fn put(&mut self, tx_id: TxId, record: &[u8]) -> io::Result<()> {
+        self.records_bytes += record.len();
+        if let Some(replaced) = self.records.insert(tx_id, record.to_vec()) {
+            self.records_bytes -= replaced.len();
+        }
+        Ok(())
+    }
+
+    fn contains(&self, tx_id: TxId) -> bool {
+        self.records.contains_key(&tx_id)
+    }
+
+    fn take(&mut self, tx_id: TxId) -> io::Result<Option<Vec<u8>>> {
+        let record_opt = self.records.remove(&tx_id);
+        if let Some(record) = &record_opt {
+            self.records_bytes -= record.len();
+        }
+        Ok(record_opt)
+    }
+
+    fn memory_usage(&self) -> usize {
+        hash_map_bytes::<TxId, Vec<u8>>(self.records.capacity()) + self.records_bytes
+    }
+}
+
+impl FileTxStore {
+    /// Create (or truncate) the file at the path, and keep the transactions
+    /// there.
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        Ok(Self {
+            file,
+            file_len: 0,
+            offsets: Default::default(),
+        })
+    }
+}
+
+impl TxStore for FileTxStore {
+    fn put(&mut self, tx_id: TxId, record: &[u8]) -> io::Result<()> {
+        let record_len = u8::try_from(record.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "record too long"))?;
+        self.file.seek(SeekFrom::Start(self.file_len))?;
+        self.file.write_all(record)?;
+        self.offsets.insert(tx_id, (self.file_len, record_len));
+        self.file_len += u64::from(record_len);
+        Ok(())
+    }
+
+    fn contains(&self, tx_id: TxId) -> bool {
+        self.offsets.contains_key(&tx_id)
+    }
+
+    fn take(&mut self, tx_id: TxId) -> io::Result<Option<Vec<u8>>> {
+        let Some(&(offset, record_len)) = self.offsets.get(&tx_id) else {
+            return Ok(None);
+        };
+        let mut record = vec![0; record_len.into()];
+        self.file.seek(SeekFrom::Start(offset))?;
+        self.file.read_exact(&mut record)?;
+        self.offsets.remove(&tx_id);
+        Ok(Some(record))
+    }
+
+    fn memory_usage(&self) -> usize {
+        hash_map_bytes::<TxId, (u64, u8)>(self.offsets.capacity())
+    }
+}
+
+const TAG_DEPOSITED: u8 = 0;
+const TAG_WITHDRAWN: u8 = 1;
+
+impl TxState {
+    /// Encode an evictable transaction (i.e. not disputed).
+    pub(super) fn encode(&self) -> Vec<u8> {
+        let mut record = vec![];
+        match *self {
+            TxState::Deposited {
+                amount_deposited,
+                client_id,
+                sender_opt,
+                deposited_at,
+                deposit_timestamp_opt,
+                dispute_count,
+            } => {
+                record.push(TAG_DEPOSITED);
+                record.extend(Amount::from(amount_deposited).into_bits().to_le_bytes());
+                record.extend(ClientIdRepr::from(client_id).to_le_bytes());
+                record.extend(
+                    sender_opt
+                        .map(ClientIdRepr::from)
+                        .unwrap_or_default()
+                        .to_le_bytes(),
+                );
+                record.push(sender_opt.is_some().into());
+                record.extend(deposited_at.to_le_bytes());
+                record.extend(
+                    deposit_timestamp_opt
+                        .map(u64::from)
+                        .unwrap_or_default()
+                        .to_le_bytes(),
+                );
+                record.push(deposit_timestamp_opt.is_some().into());
+                record.extend(dispute_count.to_le_bytes());
+            }
+            TxState::Withdrawn {
+                amount_withdrawn,
+                client_id,
+            } => {
+                record.push(TAG_WITHDRAWN);
+                record.extend(Amount::from(amount_withdrawn).into_bits().to_le_bytes());
+                record.extend(ClientIdRepr::from(client_id).to_le_bytes());
+            }
+            TxState::Disputed { .. } => unreachable!("disputed transactions are not evicted"),
+        }
+        record
+    }
+
+    /// Decode a transaction encoded by [`TxState::encode`].
+    pub(super) fn decode(record: &[u8]) -> io::Result<Self> {
+        let mut reader = Reader(record);
+        let tx_state = match reader.take::<1>()? {
+            [TAG_DEPOSITED] => {
+                let amount_deposited = reader.amount()?;
+                let client_id = reader.client_id()?;
+                let sender_id = reader.client_id()?;
+                let sender_opt = reader.flag()?.then_some(sender_id);
+                let deposited_at = u64::from_le_bytes(reader.take()?);
+                let deposit_timestamp = Timestamp::from(u64::from_le_bytes(reader.take()?));
+                let deposit_timestamp_opt = reader.flag()?.then_some(deposit_timestamp);
+                let dispute_count = u32::from_le_bytes(reader.take()?);
+                TxState::Deposited {
+                    amount_deposited,
+                    client_id,
+                    sender_opt,
+                    deposited_at,
+                    deposit_timestamp_opt,
+                    dispute_count,
+                }
+            }
+            [TAG_WITHDRAWN] => TxState::Withdrawn {
+                amount_withdrawn: reader.amount()?,
+                client_id: reader.client_id()?,
+            },
+            _ => return Err(invalid_record()),
+        };
+        if !reader.0.is_empty() {
+            return Err(invalid_record());
+        }
+        Ok(tx_state)
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        let (head, tail) = self.0.split_first_chunk().ok_or_else(invalid_record)?;
+        self.0 = tail;
+        Ok(*head)
+    }
+
+    fn flag(&mut self) -> io::Result<bool> {
+        match self.take::<1>()? {
+            [0] => Ok(false),
+            [1] => Ok(true),
+            _ => Err(invalid_record()),
+        }
+    }
+
+    fn amount(&mut self) -> io::Result<PositiveAmount> {
+        let amount = Amount::from_bits(i128::from_le_bytes(self.take()?));
+        PositiveAmount::try_from(amount).map_err(|_| invalid_record())
+    }
+
+    fn client_id(&mut self) -> io::Result<ClientId> {
+        Ok(ClientId::from(ClientIdRepr::from_le_bytes(self.take()?)))
+    }
+}
+
+fn invalid_record() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "invalid transaction record")
+}
@@ -1,8 +1,19 @@
 //! Data types to process input: transaction and its parts.
 
-use crate::types::{ClientId, PositiveAmount, TxId};
+use crate::types::{ClientId, NonNegativeAmount, PositiveAmount, Timestamp, TxId};
 
+pub mod amount_format;
+pub mod binary;
+mod byte_record;
+pub mod config;
+pub mod errors;
 mod impl_serde;
+pub mod jsonl;
+pub mod precision;
+mod rows;
+
+pub use byte_record::ByteRecordParser;
+pub use rows::{PipelinedRows, Row, Rows};
 
 /// A transaction of any supported kind.
 #[derive(Debug, Clone)]
@@ -15,6 +26,9 @@ pub struct Tx {
 
     /// See [`TxKind`].
     pub kind: TxKind,
+
+    /// When the transaction took place, if known.
+    pub timestamp: Option<Timestamp>,
 }
 
 /// Data specific to the transaction kind.
@@ -24,6 +38,8 @@ pub enum TxKind {
     Deposit(TxDeposit),
     /// See [`TxWithdrawal`].
     Withdrawal(TxWithdrawal),
+    /// See [`TxTransfer`].
+    Transfer(TxTransfer),
     /// Initiate a dispute.
     Dispute,
     /// Cancel the previously raised dispute: unhold the disputed funds.
@@ -46,5 +62,25 @@ pub struct TxWithdrawal {
     pub amount_withdrawn: PositiveAmount,
 }
 
+/// Move funds from the account to another client's account.
+#[derive(Debug, Clone)]
+pub struct TxTransfer {
+    /// Client receiving the funds.
+    pub to_client_id: ClientId,
+    /// Amount to transfer
+    pub amount_transferred: PositiveAmount,
+}
+
+/// A credit line granted to a client.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct CreditLimit {
+    /// Client the credit line is granted to.
+    #[serde(rename = "client")]
+    pub client_id: ClientId,
+
+    /// How far below zero the client's available funds may go.
+    pub credit_limit: NonNegativeAmount,
+}
+
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,157 @@
+//! The amounts written otherwise than `1234.5678`: with another decimal
+//! separator, grouped into thousands, signed with `+`, or with a currency
+//! symbol, e.g. `€ 1.234,56`.
+//!
+//! Such an amount is rewritten into the plain form before it is parsed: the
+//! parsing stays exact, and the zero and negative amounts are still rejected.
+
+use std::borrow::Cow;
+
+use crate::input::errors::{InvalidAmount, InvalidInputConfig};
+
+/// How the amounts are written in the input (see
+/// [`InputConfig::amount_format`](crate::input::config::InputConfig::amount_format)).
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AmountFormat {
+    /// Separates the fraction, e.g. `,` in `1234,56`.
+    pub decimal_separator: char,
+
+    /// Groups the digits of the integer part by three, e.g. `.` in
+    /// `1.234,56`; `None` — the digits are not grouped.
+    pub thousands_separator: Option<char>,
+
+    /// Whether an amount may be prefixed with `+`.
+    pub plus_sign: bool,
+
+    /// The currency symbols an amount may be prefixed or suffixed with, e.g.
+    /// `["€", "EUR"]`.
+    pub currency_symbols: Vec<String>,
+}
+
+impl Default for AmountFormat {
+    fn default() -> Self {
+        Self {
+            decimal_separator: '.',
+            thousands_separator: None,
+            plus_sign: false,
+            currency_symbols: vec![],
+        }
+    }
+}
+
+impl AmountFormat {
+    /// Check that the amounts in the format can be told apart: the separators
+    /// are neither digits nor signs, nor the same; a currency symbol has none
+    /// of those in it.
+    pub fn validate(&self) -> Result<(), InvalidInputConfig> {
+        let is_reserved = |c: char| c.is_ascii_digit() || c == '+' || c == '-';
+        let separators = [Some(self.decimal_separator), self.thousands_separator];
+        for separator in separators.into_iter().flatten() {
+            if is_reserved(separator) || separator.is_whitespace() {
+                return Err(InvalidInputConfig::InvalidSeparator(separator));
+            }
+        }
+        if self.thousands_separator == Some(self.decimal_separator) {
+            return Err(InvalidInputConfig::InvalidSeparator(self.decimal_separator));
+        }
+        for symbol in &self.currency_symbols {
+            let is_separator = |c| separators.contains(&Some(c));
+            if symbol.is_empty() || symbol.chars().any(|c| is_reserved(c) || is_separator(c)) {
+                return Err(InvalidInputConfig::InvalidCurrencySymbol(symbol.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Rewrite the amount into the plain form, e.g. `€ +1.234,56` into
+    /// `1234.56` (borrowed, if already plain).
+    pub fn normalize<'a>(&self, text: &'a str) -> Result<Cow<'a, str>, InvalidAmount> {
+        let malformed = || InvalidAmount::Malformed(text.to_owned());
+
+        let mut rest = text.trim();
+        let mut sign_opt = self.strip_sign(&mut rest, text)?;
+        let prefixed = self.strip_currency_symbol(&mut rest, false);
+        if sign_opt.is_none() && prefixed {
+            sign_opt = self.strip_sign(&mut rest, text)?;
+        }
+        if !prefixed {
+            self.strip_currency_symbol(&mut rest, true);
+        }
+
+        let (integer, fraction_opt) = match rest.split_once(self.decimal_separator) {
+            Some((integer, fraction)) => (integer, Some(fraction)),
+            None => (rest, None),
+        };
+        let integer = self.ungrouped(integer, text)?;
+        let is_digits = |part: &str| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+        if !is_digits(&integer) || fraction_opt.is_some_and(|fraction| !is_digits(fraction)) {
+            return Err(malformed());
+        }
+
+        let mut plain = String::with_capacity(text.len());
+        plain.extend(sign_opt);
+        plain.push_str(&integer);
+        if let Some(fraction) = fraction_opt {
+            plain.push('.');
+            plain.push_str(fraction);
+        }
+        if plain == text {
+            Ok(Cow::Borrowed(text))
+        } else {
+            Ok(Cow::Owned(plain))
+        }
+    }
+
+    /// Strip the leading sign: `-` kept (the amount is rejected later, as a
+    /// negative), `+` dropped (if allowed).
+    fn strip_sign(&self, rest: &mut &str, text: &str) -> Result<Option<char>, InvalidAmount> {
+        if let Some(unsigned) = rest.strip_prefix('-') {
+            *rest = unsigned.trim_start();
+            Ok(Some('-'))
+        } else if let Some(unsigned) = rest.strip_prefix('+') {
+            if !self.plus_sign {
+                return Err(InvalidAmount::PlusSign(text.to_owned()));
+            }
+            *rest = unsigned.trim_start();
+            Ok(None)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Strip a currency symbol off the start (or the end, if `suffix`).
+    fn strip_currency_symbol(&self, rest: &mut &str, suffix: bool) -> bool {
+        let stripped_opt = self.currency_symbols.iter().find_map(|symbol| {
+            if suffix {
+                rest.strip_suffix(symbol.as_str())
+            } else {
+                rest.strip_prefix(symbol.as_str())
+            }
+        });
+        if let Some(stripped) = stripped_opt {
+            *rest = stripped.trim();
+        }
+        stripped_opt.is_some()
+    }
+
+    /// The integer part without the thousands separators, checking that they
+    /// group the digits by three.
+    fn ungrouped<'a>(&self, integer: &'a str, text: &str) -> Result<Cow<'a, str>, InvalidAmount> {
+        let Some(thousands_separator) = self.thousands_separator else {
+            return Ok(Cow::Borrowed(integer));
+        };
+        if !integer.contains(thousands_separator) {
+            return Ok(Cow::Borrowed(integer));
+        }
+        let mut groups = integer.split(thousands_separator);
+        let leading = groups.next().expect("split yields at least one");
+        if leading.is_empty() || leading.len() > 3 || groups.clone().any(|group| group.len() != 3) {
+            return Err(InvalidAmount::Grouping(text.to_owned()));
+        }
+        Ok(Cow::Owned(integer.replace(thousands_separator, "")))
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,60 @@
+use test_case::test_case;
+
+use crate::input::amount_format::AmountFormat;
+
+fn european() -> AmountFormat {
+    AmountFormat {
+        decimal_separator: ',',
+        thousands_separator: Some('.'),
+        plus_sign: true,
+        currency_symbols: vec!["€".into(), "EUR".into()],
+    }
+}
+
+#[test_case("1234,5678", Ok("1234.5678"))]
+#[test_case("1.234,56", Ok("1234.56"))]
+#[test_case("1.234.567", Ok("1234567"))]
+#[test_case("12", Ok("12"))]
+#[test_case("+1.234,56", Ok("1234.56"); "plus-sign")]
+#[test_case("-1.234,56", Ok("-1234.56"); "minus-sign")]
+#[test_case("€1.234,56", Ok("1234.56"); "currency-prefix")]
+#[test_case("€ -5", Ok("-5"))]
+#[test_case("+ 1,5 EUR", Ok("1.5"))]
+#[test_case("0,00", Ok("0.00"))]
+#[test_case(
+    "1,234.56",
+    Err("invalid amount: `1,234.56` does not match the amount format")
+)]
+#[test_case(
+    "1.23,4",
+    Err("invalid amount: `1.23,4`: misplaced thousands separator")
+)]
+#[test_case(
+    ".234,5",
+    Err("invalid amount: `.234,5`: misplaced thousands separator")
+)]
+#[test_case(
+    "1234.5",
+    Err("invalid amount: `1234.5`: misplaced thousands separator")
+)]
+#[test_case("1,", Err("invalid amount: `1,` does not match the amount format"))]
+#[test_case("€", Err("invalid amount: `€` does not match the amount format"))]
+#[test_case("$5", Err("invalid amount: `$5` does not match the amount format"); "unknown-currency")]
+#[test_case("€5€", Err("invalid amount: `€5€` does not match the amount format"); "currency-twice")]
+fn normalize_european(input: &str, expected: Result<&str, &str>) {
+    let output = european().normalize(input).map_err(|e| e.to_string());
+    assert_eq!(output.as_deref().map_err(String::as_str), expected);
+}
+
+#[test_case("1234.5678", Ok("1234.5678"))]
+#[test_case("+1", Err("invalid amount: `+1`: `+` sign not allowed"))]
+#[test_case(
+    "1,234.5",
+    Err("invalid amount: `1,234.5` does not match the amount format")
+)]
+fn normalize_plain(input: &str, expected: Result<&str, &str>) {
+    let output = AmountFormat::default()
+        .normalize(input)
+        .map_err(|e| e.to_string());
+    assert_eq!(output.as_deref().map_err(String::as_str), expected);
+}
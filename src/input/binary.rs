@@ -0,0 +1,298 @@
+//! A compact binary format of the transactions: for the high-volume feeds,
+//! sparing the cost of parsing the text.
+//!
+//! The file starts with a header: the magic bytes `TXS`, and the version of
+//! the format (currently `1`; the bits `0x10` and `0x20` are set in it by the
+//! builds with the features `wide-client-ids` and `wide-tx-ids`
+//! respectively: a file is read only by a build with the same ids). The
+//! header is followed by the records, all of the same length ([`RECORD_LEN`]
+//! bytes), the integers little-endian:
+//!
+//! | bytes  | field                                                       |
+//! |--------|-------------------------------------------------------------|
+//! | 1      | kind: deposit, withdrawal, transfer, dispute, resolve, chargeback (`0..=5`) |
+//! | 1      | flags: `1` if the timestamp is present                       |
+//! | 2 (4)  | client (u16; u32 with `wide-client-ids`)                    |
+//! | 4 (8)  | tx (u32; u64 with `wide-tx-ids`)                            |
+//! | 16     | amount (i128, the raw fixed-point value); `0` if none       |
+//! | 2 (4)  | to_client (as client); `0` unless a transfer                |
+//! | 8      | timestamp (u64); `0` if none                                |
+//!
+//! A malformed record fails itself only: the next one is read as usual.
+
+use std::io;
+
+use crate::{
+    input::{Tx, TxDeposit, TxKind, TxTransfer, TxWithdrawal},
+    types::{Amount, ClientId, ClientIdRepr, PositiveAmount, Timestamp, TxIdRepr},
+};
+
+/// The bytes the file starts with.
+pub const MAGIC: [u8; 3] = *b"TXS";
+
+/// The version of the format written (and read), the id widths included.
+pub const VERSION: u8 = 1 | WIDE_CLIENT_IDS | WIDE_TX_IDS;
+
+/// The length of a record (in bytes).
+pub const RECORD_LEN: usize =
+    2 + 2 * size_of::<ClientIdRepr>() + size_of::<TxIdRepr>() + size_of::<i128>() + 8;
+
+const WIDE_CLIENT_IDS: u8 = if cfg!(feature = "wide-client-ids") {
+    0x10
+} else {
+    0
+};
+const WIDE_TX_IDS: u8 = if cfg!(feature = "wide-tx-ids") {
+    0x20
+} else {
+    0
+};
+
+/// A record: its index, and the transaction decoded (an error of the kind
+/// [`io::ErrorKind::InvalidData`] if the record is malformed).
+pub type BinaryRow = (usize, io::Result<Tx>);
+
+const TAG_DEPOSIT: u8 = 0;
+const TAG_WITHDRAWAL: u8 = 1;
+const TAG_TRANSFER: u8 = 2;
+const TAG_DISPUTE: u8 = 3;
+const TAG_RESOLVE: u8 = 4;
+const TAG_CHARGEBACK: u8 = 5;
+
+const FLAG_TIMESTAMP: u8 = 1;
+
+/// Writes the transactions in the binary format.
+#[derive(Debug)]
+pub struct BinaryWriter<W> {
+    writer: W,
+}
+
+/// The records of a file in the binary format.
+#[derive(Debug)]
+pub struct BinaryRows<R> {
+    reader: R,
+    row_idx: usize,
+    done: bool,
+}
+
+impl<W: io::Write> BinaryWriter<W> {
+    /// Write the header, and prepare to write the records.
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&[VERSION])?;
+        Ok(Self { writer })
+    }
+
+    /// Write the transaction as a record.
+    pub fn write(&mut self, tx: &Tx) -> io::Result<()> {
+        self.writer.write_all(&encode(tx))
+    }
+
+    /// Flush the records written, and return the underlying writer.
+    pub fn into_inner(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+impl<R: io::Read> BinaryRows<R> {
+    /// Read and check the header, and prepare to read the records.
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let mut header = [0; MAGIC.len() + 1];
+        reader.read_exact(&mut header)?;
+        let (magic, version) = header.split_at(MAGIC.len());
+        if magic != MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a binary transactions file",
+            ));
+        }
+        if version != [VERSION] {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported binary format version: {}", version[0]),
+            ));
+        }
+        Ok(Self {
+            reader,
+            row_idx: 0,
+            done: false,
+        })
+    }
+
+    /// Read the next record: `None` at the end of the file.
+    fn read_record(&mut self) -> io::Result<Option<[u8; RECORD_LEN]>> {
+        let mut record = [0; RECORD_LEN];
+        let mut filled = 0;
+        while filled < RECORD_LEN {
+            match self.reader.read(&mut record[filled..]) {
+                Ok(0) if filled == 0 => return Ok(None),
+                Ok(0) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "truncated transaction record",
+                    ));
+                }
+                Ok(len) => filled += len,
+                Err(reason) if reason.kind() == io::ErrorKind::Interrupted => (),
+                Err(reason) => return Err(reason),
+            }
+        }
+        Ok(Some(record))
+    }
+}
+
+impl<R: io::Read> Iterator for BinaryRows<R> {
+    type Item = io::Result<BinaryRow>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.read_record() {
+            Ok(Some(record)) => {
+                let row_idx = self.row_idx;
+                self.row_idx += 1;
+                Some(Ok((row_idx, decode(&record))))
+            }
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(reason) => {
+                self.done = true;
+                Some(Err(reason))
+            }
+        }
+    }
+}
+
+fn encode(tx: &Tx) -> [u8; RECORD_LEN] {
+    let (tag, amount_opt, to_client_id_opt) = match tx.kind {
+        TxKind::Deposit(TxDeposit { amount_deposited }) => {
+            (TAG_DEPOSIT, Some(amount_deposited), None)
+        }
+        TxKind::Withdrawal(TxWithdrawal { amount_withdrawn }) => {
+            (TAG_WITHDRAWAL, Some(amount_withdrawn), None)
+        }
+        TxKind::Transfer(TxTransfer {
+            to_client_id,
+            amount_transferred,
+        }) => (TAG_TRANSFER, Some(amount_transferred), Some(to_client_id)),
+        TxKind::Dispute => (TAG_DISPUTE, None, None),
+        TxKind::Resolve => (TAG_RESOLVE, None, None),
+        TxKind::Chargeback => (TAG_CHARGEBACK, None, None),
+    };
+    let flags = if tx.timestamp.is_some() {
+        FLAG_TIMESTAMP
+    } else {
+        0
+    };
+
+    let mut record = [0; RECORD_LEN];
+    let mut writer = Writer(&mut record);
+    writer.put(&[tag, flags]);
+    writer.put(&ClientIdRepr::from(tx.client_id).to_le_bytes());
+    writer.put(&TxIdRepr::from(tx.tx_id).to_le_bytes());
+    writer.put(
+        &amount_opt
+            .map(|amount| Amount::from(amount).into_bits())
+            .unwrap_or_default()
+            .to_le_bytes(),
+    );
+    writer.put(
+        &to_client_id_opt
+            .map(ClientIdRepr::from)
+            .unwrap_or_default()
+            .to_le_bytes(),
+    );
+    writer.put(
+        &tx.timestamp
+            .map(u64::from)
+            .unwrap_or_default()
+            .to_le_bytes(),
+    );
+    record
+}
+
+fn decode(record: &[u8; RECORD_LEN]) -> io::Result<Tx> {
+    let mut reader = Reader(record);
+    let [tag, flags] = reader.take();
+    let client_id = ClientId::from(ClientIdRepr::from_le_bytes(reader.take()));
+    let tx_id = TxIdRepr::from_le_bytes(reader.take()).into();
+    let amount_bits = i128::from_le_bytes(reader.take());
+    let to_client_id = ClientIdRepr::from_le_bytes(reader.take());
+    let timestamp = u64::from_le_bytes(reader.take());
+
+    let amount = || {
+        PositiveAmount::try_from(Amount::from_bits(amount_bits))
+            .map_err(|reason| invalid_record(reason.to_string()))
+    };
+    let no_amount = || match amount_bits {
+        0 => Ok(()),
+        _ => Err(invalid_record("unexpected amount".into())),
+    };
+    let kind = match tag {
+        TAG_DEPOSIT => TxKind::Deposit(TxDeposit {
+            amount_deposited: amount()?,
+        }),
+        TAG_WITHDRAWAL => TxKind::Withdrawal(TxWithdrawal {
+            amount_withdrawn: amount()?,
+        }),
+        TAG_TRANSFER => TxKind::Transfer(TxTransfer {
+            to_client_id: to_client_id.into(),
+            amount_transferred: amount()?,
+        }),
+        TAG_DISPUTE => no_amount().map(|()| TxKind::Dispute)?,
+        TAG_RESOLVE => no_amount().map(|()| TxKind::Resolve)?,
+        TAG_CHARGEBACK => no_amount().map(|()| TxKind::Chargeback)?,
+        _ => return Err(invalid_record(format!("unknown kind: {}", tag))),
+    };
+    if !matches!(kind, TxKind::Transfer(_)) && to_client_id != 0 {
+        return Err(invalid_record("unexpected to_client".into()));
+    }
+    let timestamp = match flags {
+        0 if timestamp == 0 => None,
+        FLAG_TIMESTAMP => Some(Timestamp::from(timestamp)),
+        0 => return Err(invalid_record("unexpected timestamp".into())),
+        _ => return Err(invalid_record(format!("unknown flags: {:#04x}", flags))),
+    };
+
+    Ok(Tx {
+        client_id,
+        tx_id,
+        kind,
+        timestamp,
+    })
+}
+
+struct Writer<'a>(&'a mut [u8]);
+
+impl Writer<'_> {
+    fn put(&mut self, bytes: &[u8]) {
+        let (head, tail) = std::mem::take(&mut self.0).split_at_mut(bytes.len());
+        head.copy_from_slice(bytes);
+        self.0 = tail;
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    /// Take the next field; the fields are laid out to fill the record.
+    fn take<const N: usize>(&mut self) -> [u8; N] {
+        let (head, tail) = self
+            .0
+            .split_first_chunk()
+            .expect("the fields fit the record");
+        self.0 = tail;
+        *head
+    }
+}
+
+fn invalid_record(reason: String) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("invalid transaction record: {}", reason),
+    )
+}
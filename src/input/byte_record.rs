@@ -0,0 +1,328 @@
+//! A fast path to parse transactions: directly from the fields of a
+//! [`csv::ByteRecord`], without serde and without allocations.
+//!
+//! Only the well-formed rows are parsed here; anything unusual (a malformed
+//! or untrimmed field, a missing amount, etc.) is left to the serde path (see
+//! `impl_serde.rs`), which is then responsible for the error report. Hence
+//! the results of the both paths are identical.
+//!
+//! The names of the columns and of the transaction types are mapped onto the
+//! expected ones according to the [`InputConfig`], and the amounts rewritten
+//! into the plain form and rounded (if so configured) before serde sees them.
+//! In the strict mode, the header and every row are checked against the
+//! expected columns before being parsed.
+
+use std::{borrow::Cow, str};
+
+use csv::ByteRecord;
+
+use crate::{
+    input::{
+        Tx, TxDeposit, TxKind, TxTransfer, TxWithdrawal,
+        config::{Column, InputConfig, TxType},
+        errors::{InvalidAmount, InvalidHeader, ParseTxError, StrictInputError},
+        precision::RoundingCounter,
+    },
+    types::{Amount, ClientIdRepr, PositiveAmount, PreciseAmount, Timestamp, TxIdRepr},
+};
+
+/// Parses the records of a CSV-file with the given header.
+#[derive(Debug, Clone)]
+pub struct ByteRecordParser {
+    headers: ByteRecord,
+    columns_opt: Option<Columns>,
+    config: InputConfig,
+    rounding_counter: RoundingCounter,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Columns {
+    kind: Option<usize>,
+    client_id: Option<usize>,
+    tx_id: Option<usize>,
+    amount: Option<usize>,
+    to_client_id: Option<usize>,
+    timestamp: Option<usize>,
+}
+
+impl ByteRecordParser {
+    /// The parser for the records following the header.
+    pub fn new(headers: &ByteRecord) -> Self {
+        Self::with_config(headers, &InputConfig::default())
+            .expect("the header is checked in the strict mode only")
+    }
+
+    /// The parser for the records following the header, in the strict mode:
+    /// the header is to consist of the known columns (`type`, `client`, `tx`
+    /// and `amount` being required), and each record is to match it.
+    pub fn strict(headers: &ByteRecord) -> Result<Self, InvalidHeader> {
+        let config = InputConfig {
+            strict: true,
+            ..Default::default()
+        };
+        Self::with_config(headers, &config)
+    }
+
+    /// The parser for the records following the header, the names in which
+    /// are mapped according to the config; the header is checked if strict.
+    pub fn with_config(headers: &ByteRecord, config: &InputConfig) -> Result<Self, InvalidHeader> {
+        let mut columns = Columns::default();
+        let mut has_duplicates = false;
+        for (field, name) in headers.iter().enumerate() {
+            let name_lossy = || String::from_utf8_lossy(name).into_owned();
+            let Some(column) = config.column(name) else {
+                if config.strict {
+                    return Err(InvalidHeader::UnexpectedColumn {
+                        field,
+                        name: name_lossy(),
+                    });
+                }
+                continue;
+            };
+            if columns.get_mut(column).replace(field).is_some() {
+                if config.strict {
+                    return Err(InvalidHeader::DuplicateColumn {
+                        field,
+                        name: name_lossy(),
+                    });
+                }
+                // left to serde to report
+                has_duplicates = true;
+            }
+        }
+        if config.strict {
+            for column in [Column::Type, Column::Client, Column::Tx, Column::Amount] {
+                if columns.get_mut(column).is_none() {
+                    return Err(InvalidHeader::MissingColumn(column.name()));
+                }
+            }
+        }
+
+        // serde expects the columns under their own names.
+        let headers = headers
+            .iter()
+            .map(|name| {
+                config
+                    .column(name)
+                    .map_or(name, |column| column.name().as_bytes())
+            })
+            .collect();
+        Ok(Self {
+            headers,
+            columns_opt: (!has_duplicates).then_some(columns),
+            config: config.clone(),
+            rounding_counter: RoundingCounter::default(),
+        })
+    }
+
+    /// Parse the record: on the fast path, if possible; via serde otherwise.
+    pub fn parse(&self, record: &ByteRecord) -> Result<Tx, ParseTxError> {
+        if self.config.strict {
+            self.check_strict(record)?;
+        }
+        if let Some(tx) = self.parse_fast(record) {
+            return Ok(tx);
+        }
+        let (normalized_opt, adjustment_opt) = self.normalize(record)?;
+        let record = normalized_opt.as_ref().unwrap_or(record);
+        let tx = record.deserialize::<Tx>(Some(&self.headers))?;
+        if let Some(adjustment) = adjustment_opt
+            && matches!(
+                tx.kind,
+                TxKind::Deposit(_) | TxKind::Withdrawal(_) | TxKind::Transfer(_)
+            )
+        {
+            self.rounding_counter.count(adjustment);
+        }
+        Ok(tx)
+    }
+
+    /// The counter of the rows the amounts of which were rounded.
+    pub fn rounding_counter(&self) -> RoundingCounter {
+        self.rounding_counter.clone()
+    }
+
+    /// The record as serde expects it, if given otherwise: the transaction
+    /// type under the expected name, and the amount in the plain form and
+    /// rounded (if configured); also the adjustment made by the rounding, if
+    /// any.
+    fn normalize(
+        &self,
+        record: &ByteRecord,
+    ) -> Result<(Option<ByteRecord>, Option<PreciseAmount>), InvalidAmount> {
+        let Some(columns) = self.columns_opt else {
+            return Ok((None, None));
+        };
+
+        let kind_opt = columns.kind.and_then(|field| {
+            let kind = record.get(field)?;
+            if !self.config.maps_names() {
+                return None;
+            }
+            let expected_name = self.config.tx_type(kind)?.name().as_bytes();
+            (kind != expected_name).then_some((field, expected_name.to_vec()))
+        });
+        let mut amount_opt = None;
+        if let Some(field) = columns.amount
+            && self.config.rewrites_amounts()
+            && let Some(amount) = record.get(field).filter(|amount| !amount.is_empty())
+            && let Ok(amount) = str::from_utf8(amount)
+            && let (Cow::Owned(normalized), adjustment_opt) =
+                self.config.normalize_amount(amount)?
+        {
+            amount_opt = Some((field, normalized.into_bytes(), adjustment_opt));
+        }
+        if kind_opt.is_none() && amount_opt.is_none() {
+            return Ok((None, None));
+        }
+
+        let mut normalized = record.clone();
+        normalized.clear();
+        for (field, value) in record.iter().enumerate() {
+            match (&kind_opt, &amount_opt) {
+                (Some((kind_field, kind)), _) if field == *kind_field => {
+                    normalized.push_field(kind)
+                }
+                (_, Some((amount_field, amount, _))) if field == *amount_field => {
+                    normalized.push_field(amount)
+                }
+                _ => normalized.push_field(value),
+            }
+        }
+        Ok((
+            Some(normalized),
+            amount_opt.and_then(|(_, _, adjustment_opt)| adjustment_opt),
+        ))
+    }
+
+    /// Check the record against the header, and the values present against
+    /// the transaction type.
+    fn check_strict(&self, record: &ByteRecord) -> Result<(), StrictInputError> {
+        if record.len() != self.headers.len() {
+            return Err(StrictInputError::FieldCount {
+                fields: record.len(),
+                header_fields: self.headers.len(),
+            });
+        }
+        let columns = self.columns_opt.expect("the strict header is valid");
+        let kind_field = columns.kind.expect("the strict header has `type`");
+        let kind = &record[kind_field];
+        let (has_amount, has_to_client) = match self.config.tx_type(kind) {
+            Some(TxType::Deposit | TxType::Withdrawal) => (true, false),
+            Some(TxType::Transfer) => (true, true),
+            Some(TxType::Dispute | TxType::Resolve | TxType::Chargeback) => (false, false),
+            None => {
+                return Err(StrictInputError::UnknownType {
+                    field: kind_field,
+                    value: String::from_utf8_lossy(kind).into_owned(),
+                });
+            }
+        };
+        for (column, name, expected) in [
+            (columns.amount, "amount", has_amount),
+            (columns.to_client_id, "to_client", has_to_client),
+        ] {
+            if let Some(field) = column
+                && !expected
+                && !record[field].is_empty()
+            {
+                return Err(StrictInputError::UnexpectedValue {
+                    field,
+                    column: name,
+                    kind: String::from_utf8_lossy(kind).into_owned(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn parse_fast(&self, record: &ByteRecord) -> Option<Tx> {
+        let columns = self.columns_opt?;
+        if record.len() > self.headers.len() {
+            return None;
+        }
+        let field = |column: Option<usize>| {
+            column
+                .and_then(|idx| record.get(idx))
+                .filter(|field| !field.is_empty())
+        };
+
+        let client_id = parse_uint::<ClientIdRepr>(field(columns.client_id)?)?.into();
+        let tx_id = parse_uint::<TxIdRepr>(field(columns.tx_id)?)?.into();
+        // a malformed optional field fails the row, whatever its kind.
+        let amount_opt = match field(columns.amount) {
+            // the amounts written otherwise are left to the serde path.
+            Some(_) if self.config.amount_format.is_some() => return None,
+            amount_field_opt => optional(amount_field_opt, parse_amount)?,
+        };
+        let to_client_id_opt =
+            optional(field(columns.to_client_id), parse_uint::<ClientIdRepr>)?.map(Into::into);
+        let timestamp = optional(field(columns.timestamp), parse_uint::<u64>)?.map(Timestamp::from);
+
+        let tx_type = self.config.tx_type(field(columns.kind)?)?;
+        let kind = match (tx_type, amount_opt, to_client_id_opt) {
+            (TxType::Deposit, Some(amount_deposited), _) => {
+                TxKind::Deposit(TxDeposit { amount_deposited })
+            }
+            (TxType::Withdrawal, Some(amount_withdrawn), _) => {
+                TxKind::Withdrawal(TxWithdrawal { amount_withdrawn })
+            }
+            (TxType::Transfer, Some(amount_transferred), Some(to_client_id)) => {
+                TxKind::Transfer(TxTransfer {
+                    to_client_id,
+                    amount_transferred,
+                })
+            }
+            (TxType::Dispute, ..) => TxKind::Dispute,
+            (TxType::Resolve, ..) => TxKind::Resolve,
+            (TxType::Chargeback, ..) => TxKind::Chargeback,
+            _ => return None,
+        };
+
+        Some(Tx {
+            client_id,
+            tx_id,
+            kind,
+            timestamp,
+        })
+    }
+}
+
+impl Columns {
+    fn get_mut(&mut self, column: Column) -> &mut Option<usize> {
+        match column {
+            Column::Type => &mut self.kind,
+            Column::Client => &mut self.client_id,
+            Column::Tx => &mut self.tx_id,
+            Column::Amount => &mut self.amount,
+            Column::ToClient => &mut self.to_client_id,
+            Column::Timestamp => &mut self.timestamp,
+        }
+    }
+}
+
+/// An optional field: `None` if absent, fails if malformed.
+fn optional<T>(field_opt: Option<&[u8]>, parse: fn(&[u8]) -> Option<T>) -> Option<Option<T>> {
+    match field_opt {
+        None => Some(None),
+        Some(field) => parse(field).map(Some),
+    }
+}
+
+/// An unsigned integer: decimal digits only.
+fn parse_uint<U>(field: &[u8]) -> Option<U>
+where
+    U: TryFrom<u64>,
+{
+    let mut value = 0u64;
+    for &byte in field {
+        let digit = byte.checked_sub(b'0').filter(|&digit| digit <= 9)?;
+        value = value.checked_mul(10)?.checked_add(digit.into())?;
+    }
+    U::try_from(value).ok()
+}
+
+fn parse_amount(field: &[u8]) -> Option<PositiveAmount> {
+    let amount = Amount::from_str_exact(str::from_utf8(field).ok()?).ok()?;
+    PositiveAmount::try_from(amount).ok()
+}
@@ -0,0 +1,262 @@
+//! The configuration the input is read with.
+
+use std::{borrow::Cow, collections::BTreeMap, fmt};
+
+use crate::{
+    input::{
+        amount_format::AmountFormat,
+        errors::{InvalidAmount, InvalidInputConfig},
+        precision,
+    },
+    types::{PreciseAmount, Rounding},
+};
+
+/// How the input is read: strictly or not, and how the names in it map onto
+/// the expected ones. Can be loaded from (and printed as) TOML; the omitted
+/// fields take their default values.
+#[derive(
+    Debug,
+    Clone,
+    Default,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize
+)]
+#[serde(default, deny_unknown_fields)]
+pub struct InputConfig {
+    /// Whether the CSV input is checked strictly (see
+    /// [`ByteRecordParser::strict`](crate::input::ByteRecordParser::strict)).
+    pub strict: bool,
+
+    /// Whether the transaction types (and their aliases) are matched
+    /// regardless of case.
+    pub case_insensitive_types: bool,
+
+    /// Further names of the transaction types, e.g. `withdraw = "withdrawal"`.
+    pub type_aliases: BTreeMap<String, TxType>,
+
+    /// The columns named differently in the input, e.g. `client_id =
+    /// "client"`.
+    pub columns: BTreeMap<String, Column>,
+
+    /// How the amounts given with more than 4 digits past the decimal point
+    /// are rounded; `None` — such amounts are rejected.
+    pub amount_rounding: Option<Rounding>,
+
+    /// How the amounts are written, if otherwise than `1234.5678`.
+    pub amount_format: Option<AmountFormat>,
+}
+
+/// A transaction type, as named in the input.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize
+)]
+#[serde(rename_all = "snake_case")]
+pub enum TxType {
+    /// `deposit`
+    Deposit,
+    /// `withdrawal`
+    Withdrawal,
+    /// `transfer`
+    Transfer,
+    /// `dispute`
+    Dispute,
+    /// `resolve`
+    Resolve,
+    /// `chargeback`
+    Chargeback,
+}
+
+/// A column of the input.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Column {
+    /// `type`
+    Type,
+    /// `client`
+    Client,
+    /// `tx`
+    Tx,
+    /// `amount`
+    Amount,
+    /// `to_client`
+    ToClient,
+    /// `timestamp`
+    Timestamp,
+}
+
+impl InputConfig {
+    /// Check the configuration for consistency: an alias may not shadow a
+    /// type's name, nor match another alias of a different type; the amount
+    /// format must be unambiguous.
+    pub fn validate(&self) -> Result<(), InvalidInputConfig> {
+        for (alias, &tx_type) in &self.type_aliases {
+            if TxType::ALL
+                .iter()
+                .any(|other| self.names_match(other.name(), alias.as_bytes()))
+            {
+                return Err(InvalidInputConfig::AliasShadowsType(alias.clone()));
+            }
+            if self.type_aliases.iter().any(|(other_alias, &other_type)| {
+                other_type != tx_type && self.names_match(other_alias, alias.as_bytes())
+            }) {
+                return Err(InvalidInputConfig::AmbiguousAlias(alias.clone()));
+            }
+        }
+        if let Some(amount_format) = &self.amount_format {
+            amount_format.validate()?;
+        }
+        Ok(())
+    }
+
+    /// The transaction type of the name (or alias) given in the input.
+    pub fn tx_type(&self, name: &[u8]) -> Option<TxType> {
+        if let Some(tx_type) = TxType::from_name(name) {
+            return Some(tx_type);
+        }
+        TxType::ALL
+            .into_iter()
+            .find(|tx_type| self.names_match(tx_type.name(), name))
+            .or_else(|| {
+                self.type_aliases
+                    .iter()
+                    .find(|(alias, _)| self.names_match(alias, name))
+                    .map(|(_, &tx_type)| tx_type)
+            })
+    }
+
+    /// The column of the name given in the input's header.
+    pub fn column(&self, name: &[u8]) -> Option<Column> {
+        let mapped_opt = str::from_utf8(name)
+            .ok()
+            .and_then(|name| self.columns.get(name));
+        mapped_opt.copied().or_else(|| {
+            Column::ALL
+                .into_iter()
+                .find(|column| column.name().as_bytes() == name)
+        })
+    }
+
+    /// Whether the names given in the input are mapped at all.
+    pub fn maps_names(&self) -> bool {
+        self.case_insensitive_types || !self.type_aliases.is_empty() || !self.columns.is_empty()
+    }
+
+    /// Whether the amounts given in the input are rewritten at all.
+    pub fn rewrites_amounts(&self) -> bool {
+        self.amount_format.is_some() || self.amount_rounding.is_some()
+    }
+
+    /// The amount in the plain form, rounded (if configured); also the
+    /// adjustment made by the rounding, if any.
+    pub(crate) fn normalize_amount<'a>(
+        &self,
+        text: &'a str,
+    ) -> Result<(Cow<'a, str>, Option<PreciseAmount>), InvalidAmount> {
+        let plain = match &self.amount_format {
+            None => Cow::Borrowed(text),
+            Some(amount_format) => amount_format.normalize(text)?,
+        };
+        let rounded_opt = self
+            .amount_rounding
+            .and_then(|rounding| precision::round(&plain, rounding));
+        Ok(match rounded_opt {
+            None => (plain, None),
+            Some((rounded, adjustment)) => (Cow::Owned(rounded.to_string()), Some(adjustment)),
+        })
+    }
+
+    fn names_match(&self, expected: &str, name: &[u8]) -> bool {
+        if self.case_insensitive_types {
+            expected.as_bytes().eq_ignore_ascii_case(name)
+        } else {
+            expected.as_bytes() == name
+        }
+    }
+}
+
+impl TxType {
+    /// All the transaction types.
+    pub const ALL: [Self; 6] = [
+        Self::Deposit,
+        Self::Withdrawal,
+        Self::Transfer,
+        Self::Dispute,
+        Self::Resolve,
+        Self::Chargeback,
+    ];
+
+    /// The name the type is expected under.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Deposit => "deposit",
+            Self::Withdrawal => "withdrawal",
+            Self::Transfer => "transfer",
+            Self::Dispute => "dispute",
+            Self::Resolve => "resolve",
+            Self::Chargeback => "chargeback",
+        }
+    }
+
+    /// The type expected under the name (exactly).
+    pub fn from_name(name: &[u8]) -> Option<Self> {
+        match name {
+            b"deposit" => Some(Self::Deposit),
+            b"withdrawal" => Some(Self::Withdrawal),
+            b"transfer" => Some(Self::Transfer),
+            b"dispute" => Some(Self::Dispute),
+            b"resolve" => Some(Self::Resolve),
+            b"chargeback" => Some(Self::Chargeback),
+            _ => None,
+        }
+    }
+}
+
+impl Column {
+    /// All the columns.
+    pub const ALL: [Self; 6] = [
+        Self::Type,
+        Self::Client,
+        Self::Tx,
+        Self::Amount,
+        Self::ToClient,
+        Self::Timestamp,
+    ];
+
+    /// The name the column is expected under.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Type => "type",
+            Self::Client => "client",
+            Self::Tx => "tx",
+            Self::Amount => "amount",
+            Self::ToClient => "to_client",
+            Self::Timestamp => "timestamp",
+        }
+    }
+}
+
+impl fmt::Display for InputConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let toml = toml::to_string(self).map_err(|_| fmt::Error)?;
+        f.write_str(&toml)
+    }
+}
+
+#[cfg(test)]
+mod tests;
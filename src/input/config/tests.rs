@@ -0,0 +1,66 @@
+use std::{fs, path::Path};
+
+use test_case::test_case;
+
+use crate::input::config::{InputConfig, TxType};
+
+#[test_case("default")]
+#[test_case("partner")]
+#[test_case("alias-shadows-type")]
+#[test_case("ambiguous-alias")]
+#[test_case("unknown-type")]
+#[test_case("unknown-column")]
+#[test_case("rounding")]
+#[test_case("european")]
+#[test_case("same-separators")]
+#[test_case("digit-in-currency-symbol")]
+fn load_config(case_name: &str) {
+    let input_file = Path::new(file!())
+        .parent()
+        .expect("file!().parent")
+        .join("cases")
+        .join(format!("{}.toml", case_name));
+    let input = fs::read_to_string(input_file).expect("fs::read_to_string");
+    let output = toml::from_str::<InputConfig>(&input)
+        .map_err(|e| e.message().to_owned())
+        .and_then(|config| {
+            config
+                .validate()
+                .map(|()| config.to_string())
+                .map_err(|e| e.to_string())
+        })
+        .unwrap_or_else(|reason| format!("error: {}\n", reason));
+    insta::with_settings!({
+        snapshot_path => "cases",
+        prepend_module_to_snapshot => false,
+    }, {
+        insta::assert_snapshot!(case_name, output);
+    });
+}
+
+#[test_case(false, "deposit", Some(TxType::Deposit))]
+#[test_case(false, "Deposit", None)]
+#[test_case(false, "withdraw", Some(TxType::Withdrawal))]
+#[test_case(false, "WITHDRAW", None)]
+#[test_case(true, "DEPOSIT", Some(TxType::Deposit))]
+#[test_case(true, "WITHDRAW", Some(TxType::Withdrawal))]
+#[test_case(true, "refund", None)]
+fn tx_type(case_insensitive_types: bool, name: &str, expected: Option<TxType>) {
+    let config = InputConfig {
+        case_insensitive_types,
+        type_aliases: [("withdraw".to_owned(), TxType::Withdrawal)].into(),
+        ..Default::default()
+    };
+    assert_eq!(config.tx_type(name.as_bytes()), expected);
+}
+
+#[test]
+fn printed_config_is_loaded_back() {
+    let config = InputConfig {
+        strict: true,
+        type_aliases: [("withdraw".to_owned(), TxType::Withdrawal)].into(),
+        ..Default::default()
+    };
+    let loaded = toml::from_str::<InputConfig>(&config.to_string()).expect("toml::from_str");
+    assert_eq!(loaded, config);
+}
@@ -0,0 +1,143 @@
+//! Error types
+
+/// An error parsing a transaction from a row of the input.
+#[derive(Debug, thiserror::Error)]
+pub enum ParseTxError {
+    /// The row is malformed.
+    #[error("{}", _0)]
+    Csv(
+        #[from]
+        #[source]
+        csv::Error,
+    ),
+
+    /// See [`StrictInputError`]
+    #[error("{}", _0)]
+    Strict(
+        #[from]
+        #[source]
+        StrictInputError,
+    ),
+
+    /// See [`InvalidAmount`]
+    #[error("{}", _0)]
+    Amount(
+        #[from]
+        #[source]
+        InvalidAmount,
+    ),
+}
+
+/// The header does not match the expected columns (in the strict mode).
+#[derive(Debug, thiserror::Error)]
+pub enum InvalidHeader {
+    /// A required column is absent.
+    #[error("invalid header: column `{}` is missing", _0)]
+    MissingColumn(&'static str),
+
+    /// A column is not among the expected ones.
+    #[error("invalid header: field {}: unexpected column `{}`", field, name)]
+    UnexpectedColumn {
+        /// The index of the column.
+        field: usize,
+        /// The name of the column.
+        name: String,
+    },
+
+    /// A column is given more than once.
+    #[error("invalid header: field {}: duplicate column `{}`", field, name)]
+    DuplicateColumn {
+        /// The index of the repeated column.
+        field: usize,
+        /// The name of the column.
+        name: String,
+    },
+}
+
+/// The row does not match the expected columns (in the strict mode).
+#[derive(Debug, thiserror::Error)]
+pub enum StrictInputError {
+    /// The row has more (or fewer) fields than the header.
+    #[error(
+        "strict input: {} fields, while the header has {}",
+        fields,
+        header_fields
+    )]
+    FieldCount {
+        /// The count of the fields in the row.
+        fields: usize,
+        /// The count of the columns in the header.
+        header_fields: usize,
+    },
+
+    /// The transaction type is not one of the supported.
+    #[error(
+        "strict input: field {} (`type`): unknown transaction type `{}`; \
+         expected one of: deposit, withdrawal, transfer, dispute, resolve, chargeback",
+        field,
+        value
+    )]
+    UnknownType {
+        /// The index of the `type` column.
+        field: usize,
+        /// The value given.
+        value: String,
+    },
+
+    /// A value is given in a column the transaction type does not have.
+    #[error(
+        "strict input: field {} (`{}`): unexpected in a {} row",
+        field,
+        column,
+        kind
+    )]
+    UnexpectedValue {
+        /// The index of the column.
+        field: usize,
+        /// The name of the column.
+        column: &'static str,
+        /// The transaction type of the row.
+        kind: String,
+    },
+}
+
+/// The amount does not match the configured
+/// [`AmountFormat`](crate::input::amount_format::AmountFormat).
+#[derive(Debug, thiserror::Error)]
+pub enum InvalidAmount {
+    /// The amount is not a number in the format.
+    #[error("invalid amount: `{}` does not match the amount format", _0)]
+    Malformed(String),
+
+    /// The thousands separators do not group the digits by three.
+    #[error("invalid amount: `{}`: misplaced thousands separator", _0)]
+    Grouping(String),
+
+    /// The amount is prefixed with `+`, which is not allowed.
+    #[error("invalid amount: `{}`: `+` sign not allowed", _0)]
+    PlusSign(String),
+}
+
+/// The input configuration is inconsistent.
+#[derive(Debug, thiserror::Error)]
+pub enum InvalidInputConfig {
+    /// An alias matches the name of a transaction type.
+    #[error("invalid input config: alias `{}` shadows a transaction type", _0)]
+    AliasShadowsType(String),
+
+    /// An alias matches another one, of a different transaction type.
+    #[error("invalid input config: alias `{}` is ambiguous", _0)]
+    AmbiguousAlias(String),
+
+    /// A separator of the amount format is a digit or a sign, or the decimal
+    /// and the thousands separators are the same.
+    #[error("invalid input config: amount format: invalid separator `{}`", _0)]
+    InvalidSeparator(char),
+
+    /// A currency symbol is empty, or has a digit, a sign or a separator in it.
+    #[error(
+        "invalid input config: amount format: invalid currency symbol `{}`",
+        _0
+    )]
+    InvalidCurrencySymbol(String),
+}
@@ -8,16 +8,24 @@
 //! be avoided.
 //!
 //! IDDQD: https://chatgpt.com/share/68e52644-bea4-800f-ae3e-47cec9dbbb66
+//!
+//! For the same reason, a JSON amount is taken as its raw text (see
+//! [`JsonTx`]): either a string, or a number exactly as written — never via
+//! a float.
 
 use serde::Deserialize;
+use serde_json::value::RawValue;
 
 use crate::{
-    input::{Tx, TxDeposit, TxKind, TxWithdrawal},
-    types::{ClientId, PositiveAmount, TxId},
+    input::{Tx, TxDeposit, TxKind, TxTransfer, TxWithdrawal},
+    types::{Amount, ClientId, PositiveAmount, Timestamp, TxId},
 };
 
+/// A transaction in JSON: the amount may be given as a string or as a number.
+pub(super) struct JsonTx(pub(super) Tx);
+
 #[derive(serde::Deserialize)]
-struct T {
+struct T<A> {
     #[serde(rename = "type")]
     kind: K,
     #[serde(rename = "client")]
@@ -25,7 +33,11 @@ struct T {
     #[serde(rename = "tx")]
     tx_id: TxId,
     #[serde(rename = "amount")]
-    amount_opt: Option<PositiveAmount>,
+    amount_opt: Option<A>,
+    #[serde(rename = "to_client")]
+    to_client_id_opt: Option<ClientId>,
+    #[serde(rename = "timestamp")]
+    timestamp_opt: Option<Timestamp>,
 }
 
 #[derive(serde::Deserialize)]
@@ -33,42 +45,100 @@ struct T {
 enum K {
     Deposit,
     Withdrawal,
+    Transfer,
     Dispute,
     Resolve,
     Chargeback,
 }
 
+/// An amount in JSON.
+struct JsonAmount(PositiveAmount);
+
 impl<'de> Deserialize<'de> for Tx {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        T::<PositiveAmount>::deserialize(deserializer)?.into_tx::<D::Error>()
+    }
+}
+
+impl<'de> Deserialize<'de> for JsonTx {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        T::<JsonAmount>::deserialize(deserializer)?
+            .into_tx::<D::Error>()
+            .map(Self)
+    }
+}
+
+impl<'de> Deserialize<'de> for JsonAmount {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: serde::Deserializer<'de>,
     {
         use serde::de::Error as _;
 
+        let raw = <&RawValue>::deserialize(deserializer)?.get();
+        let text = if raw.starts_with('"') {
+            serde_json::from_str::<String>(raw).map_err(D::Error::custom)?
+        } else {
+            raw.to_owned()
+        };
+        let amount = Amount::from_str_exact(&text).map_err(D::Error::custom)?;
+        PositiveAmount::try_from(amount)
+            .map(Self)
+            .map_err(D::Error::custom)
+    }
+}
+
+impl From<JsonAmount> for PositiveAmount {
+    fn from(JsonAmount(amount): JsonAmount) -> Self {
+        amount
+    }
+}
+
+impl<A: Into<PositiveAmount>> T<A> {
+    fn into_tx<E: serde::de::Error>(self) -> Result<Tx, E> {
         let T {
             kind,
             client_id,
             tx_id,
-            amount_opt: amount,
-        } = Deserialize::deserialize(deserializer)?;
+            amount_opt,
+            to_client_id_opt: to_client_id,
+            timestamp_opt: timestamp,
+        } = self;
+        let amount = amount_opt.map(Into::into);
 
-        let kind = match (kind, amount) {
-            (K::Deposit, Some(amount_deposited)) => TxKind::Deposit(TxDeposit { amount_deposited }),
-            (K::Withdrawal, Some(amount_withdrawn)) => {
+        let kind = match (kind, amount, to_client_id) {
+            (K::Deposit, Some(amount_deposited), _) => {
+                TxKind::Deposit(TxDeposit { amount_deposited })
+            }
+            (K::Withdrawal, Some(amount_withdrawn), _) => {
                 TxKind::Withdrawal(TxWithdrawal { amount_withdrawn })
             }
-            (K::Deposit | K::Withdrawal, None) => {
-                Err(D::Error::custom("field `amount` is missing"))?
+            (K::Transfer, Some(amount_transferred), Some(to_client_id)) => {
+                TxKind::Transfer(TxTransfer {
+                    to_client_id,
+                    amount_transferred,
+                })
+            }
+            (K::Deposit | K::Withdrawal | K::Transfer, None, _) => {
+                Err(E::custom("field `amount` is missing"))?
             }
-            (K::Dispute, _) => TxKind::Dispute,
-            (K::Resolve, _) => TxKind::Resolve,
-            (K::Chargeback, _) => TxKind::Chargeback,
+            (K::Transfer, Some(_), None) => Err(E::custom("field `to_client` is missing"))?,
+            (K::Dispute, ..) => TxKind::Dispute,
+            (K::Resolve, ..) => TxKind::Resolve,
+            (K::Chargeback, ..) => TxKind::Chargeback,
         };
 
-        Ok(Self {
+        Ok(Tx {
             client_id,
             tx_id,
             kind,
+            timestamp,
         })
     }
 }
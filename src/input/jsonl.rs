@@ -0,0 +1,159 @@
+//! Reading the transactions from a JSON Lines file: one object per line,
+//! with the same fields as the CSV columns, e.g.
+//! `{"type":"deposit","client":1,"tx":1,"amount":"1.5"}`.
+//!
+//! The amounts may be given as strings or as numbers: either is taken exactly
+//! as written.
+//!
+//! The names of the fields and of the transaction types are mapped onto the
+//! expected ones, and the amounts rewritten into the plain form and rounded,
+//! according to the [`InputConfig`] (the line is rewritten first, if so
+//! configured).
+
+use std::{borrow::Cow, collections::BTreeMap, io};
+
+use serde_json::value::RawValue;
+
+use crate::{
+    input::{
+        Tx, TxKind,
+        config::{Column, InputConfig},
+        impl_serde::JsonTx,
+        precision::RoundingCounter,
+    },
+    types::PreciseAmount,
+};
+
+/// A line: its index (the blank lines excluded), and the transaction parsed.
+pub type JsonlRow = (usize, Result<Tx, serde_json::Error>);
+
+/// The lines of a JSON Lines file.
+#[derive(Debug)]
+pub struct JsonlRows<R> {
+    reader: R,
+    config_opt: Option<InputConfig>,
+    rounding_counter: RoundingCounter,
+    line: String,
+    row_idx: usize,
+    done: bool,
+}
+
+impl<R: io::BufRead> JsonlRows<R> {
+    /// Prepare to read the lines.
+    pub fn new(reader: R) -> Self {
+        Self::with_config(reader, &InputConfig::default())
+    }
+
+    /// Prepare to read the lines, the names in which are mapped (and the
+    /// amounts rewritten) according to the config.
+    pub fn with_config(reader: R, config: &InputConfig) -> Self {
+        let rewrites = config.maps_names() || config.rewrites_amounts();
+        Self {
+            reader,
+            config_opt: rewrites.then(|| config.clone()),
+            rounding_counter: RoundingCounter::default(),
+            line: String::new(),
+            row_idx: 0,
+            done: false,
+        }
+    }
+
+    /// The counter of the rows the amounts of which were rounded.
+    pub fn rounding_counter(&self) -> RoundingCounter {
+        self.rounding_counter.clone()
+    }
+}
+
+impl<R: io::BufRead> Iterator for JsonlRows<R> {
+    type Item = io::Result<JsonlRow>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            self.line.clear();
+            match self.reader.read_line(&mut self.line) {
+                Ok(0) => self.done = true,
+                Ok(_) if self.line.trim().is_empty() => (),
+                Ok(_) => {
+                    let row_idx = self.row_idx;
+                    self.row_idx += 1;
+                    let parse_result = match &self.config_opt {
+                        None => parse_line(&self.line),
+                        Some(config) => {
+                            parse_normalized_line(&self.line, config).map(|(tx, adjustment_opt)| {
+                                if let Some(adjustment) = adjustment_opt {
+                                    self.rounding_counter.count(adjustment);
+                                }
+                                tx
+                            })
+                        }
+                    };
+                    return Some(Ok((row_idx, parse_result)));
+                }
+                Err(reason) => {
+                    self.done = true;
+                    return Some(Err(reason));
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Parse a transaction from a line of JSON.
+pub fn parse_line(line: &str) -> Result<Tx, serde_json::Error> {
+    serde_json::from_str::<JsonTx>(line).map(|JsonTx(tx)| tx)
+}
+
+/// Parse a transaction from a line of JSON, the names in which are mapped
+/// (and the amount rewritten) according to the config.
+pub fn parse_mapped_line(line: &str, config: &InputConfig) -> Result<Tx, serde_json::Error> {
+    parse_normalized_line(line, config).map(|(tx, _)| tx)
+}
+
+/// Parse a transaction from a line of JSON, rewritten as the config says;
+/// also the adjustment made by rounding the amount, if any.
+fn parse_normalized_line(
+    line: &str,
+    config: &InputConfig,
+) -> Result<(Tx, Option<PreciseAmount>), serde_json::Error> {
+    let fields = serde_json::from_str::<BTreeMap<String, &RawValue>>(line)?;
+    let mut mapped = BTreeMap::<&str, Cow<RawValue>>::new();
+    let mut adjustment_opt = None;
+    for (name, value) in &fields {
+        let name = config
+            .column(name.as_bytes())
+            .map_or(name.as_str(), |column| column.name());
+        let mut value = Cow::Borrowed(*value);
+        if name == Column::Type.name()
+            && let Ok(tx_type_name) = serde_json::from_str::<String>(value.get())
+            && let Some(tx_type) = config.tx_type(tx_type_name.as_bytes())
+        {
+            value = Cow::Owned(serde_json::value::to_raw_value(tx_type.name())?);
+        }
+        // a `null` amount is taken as absent, as an empty one is
+        if name == Column::Amount.name() && config.rewrites_amounts() && value.get() != "null" {
+            let text = serde_json::from_str::<String>(value.get())
+                .unwrap_or_else(|_| value.get().to_owned());
+            if !text.is_empty()
+                && let (Cow::Owned(normalized), adjustment) = config
+                    .normalize_amount(&text)
+                    .map_err(serde::de::Error::custom)?
+            {
+                value = Cow::Owned(serde_json::value::to_raw_value(&normalized)?);
+                adjustment_opt = adjustment;
+            }
+        }
+        if mapped.insert(name, value).is_some() {
+            return Err(serde::de::Error::custom(format!(
+                "duplicate field `{}`",
+                name
+            )));
+        }
+    }
+    let tx = parse_line(&serde_json::to_string(&mapped)?)?;
+    let has_amount = matches!(
+        tx.kind,
+        TxKind::Deposit(_) | TxKind::Withdrawal(_) | TxKind::Transfer(_)
+    );
+    Ok((tx, adjustment_opt.filter(|_| has_amount)))
+}
@@ -0,0 +1,53 @@
+//! The amounts given more precisely than an [`Amount`] is kept: rejected, or
+//! rounded (see [`InputConfig::amount_rounding`]) and counted.
+//!
+//! [`InputConfig::amount_rounding`]: crate::input::config::InputConfig::amount_rounding
+
+use std::sync::{Arc, Mutex};
+
+use crate::types::{Amount, PreciseAmount, Rounding};
+
+/// How many rows had their amounts rounded, and by how much in total.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize)]
+pub struct RoundingStats {
+    /// The count of the rows the amounts of which were rounded.
+    pub rounded_rows: u64,
+
+    /// The sum of the rounded amounts less the amounts given.
+    pub adjustment: PreciseAmount,
+}
+
+/// Counts the rows the amounts of which were rounded; shared by the clones of
+/// a parser (the threads parsing in parallel included).
+#[derive(Debug, Clone, Default)]
+pub struct RoundingCounter(Arc<Mutex<RoundingStats>>);
+
+impl RoundingCounter {
+    /// The stats counted so far.
+    pub fn stats(&self) -> RoundingStats {
+        *self.0.lock().expect("not poisoned")
+    }
+
+    pub(crate) fn count(&self, adjustment: PreciseAmount) {
+        let mut stats = self.0.lock().expect("not poisoned");
+        stats.rounded_rows += 1;
+        stats.adjustment = PreciseAmount::from_bits(
+            stats
+                .adjustment
+                .into_bits()
+                .saturating_add(adjustment.into_bits()),
+        );
+    }
+}
+
+/// Round the amount given, if more precise than an [`Amount`] (but no more
+/// than a [`PreciseAmount`]): the rounded amount, and the adjustment made.
+/// `None` — nothing to round (or the amount is malformed: left to the parser
+/// to report).
+pub(crate) fn round(text: &str, rounding: Rounding) -> Option<(Amount, PreciseAmount)> {
+    if Amount::from_str_exact(text).is_ok() {
+        return None;
+    }
+    let precise = PreciseAmount::from_str_exact(text).ok()?;
+    Some(rounding.round(precise))
+}
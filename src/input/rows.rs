@@ -0,0 +1,253 @@
+//! Reading the transactions from a CSV-file, row by row: in the current
+//! thread, or pipelined — parsed by the background threads, while the
+//! transactions parsed so far are being processed.
+//!
+//! Both yield `Ok((row_idx, parse_result))` per row; an error reading the
+//! file is yielded as `Err`, and ends the rows.
+
+use std::{
+    io,
+    num::NonZeroUsize,
+    sync::mpsc::{Receiver, SyncSender, sync_channel},
+    thread::{self, JoinHandle},
+    vec,
+};
+
+use csv::ByteRecord;
+
+use crate::input::{ByteRecordParser, Tx, errors::ParseTxError};
+
+/// A row: its index (the header excluded), and the transaction parsed.
+pub type Row = (usize, Result<Tx, ParseTxError>);
+
+/// The rows, parsed in the current thread.
+#[derive(Debug)]
+pub struct Rows<R> {
+    csv_reader: csv::Reader<R>,
+    parser: ByteRecordParser,
+    record: ByteRecord,
+    row_idx: usize,
+    done: bool,
+}
+
+/// The rows, parsed in chunks by the background threads; yielded in the
+/// original order.
+#[derive(Debug)]
+pub struct PipelinedRows {
+    parsed_rxs: Vec<Receiver<ParsedChunk>>,
+    next_rx_idx: usize,
+    rows: vec::IntoIter<Row>,
+    read_error_opt: Option<csv::Error>,
+    threads: Vec<JoinHandle<()>>,
+    done: bool,
+}
+
+/// How many chunks may be queued to (and from) each parsing thread.
+const CHANNEL_CAPACITY: usize = 4;
+
+#[derive(Debug)]
+struct RawChunk {
+    first_row_idx: usize,
+    records: Vec<ByteRecord>,
+    read_error_opt: Option<csv::Error>,
+}
+
+#[derive(Debug)]
+struct ParsedChunk {
+    rows: Vec<Row>,
+    read_error_opt: Option<csv::Error>,
+}
+
+impl<R: io::Read> Rows<R> {
+    /// Prepare to read the rows following the header (already read: the
+    /// parser is made for it).
+    pub fn new(csv_reader: csv::Reader<R>, parser: ByteRecordParser) -> Self {
+        Self {
+            csv_reader,
+            parser,
+            record: ByteRecord::new(),
+            row_idx: 0,
+            done: false,
+        }
+    }
+}
+
+impl<R: io::Read> Iterator for Rows<R> {
+    type Item = Result<Row, csv::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.csv_reader.read_byte_record(&mut self.record) {
+            Ok(true) => {
+                let row_idx = self.row_idx;
+                self.row_idx += 1;
+                Some(Ok((row_idx, self.parser.parse(&self.record))))
+            }
+            Ok(false) => {
+                self.done = true;
+                None
+            }
+            Err(reason) => {
+                self.done = true;
+                Some(Err(reason))
+            }
+        }
+    }
+}
+
+impl PipelinedRows {
+    /// Start the threads: one reading the rows following the header (already
+    /// read: the parser is made for it) in chunks of `chunk_len` rows, and
+    /// `parser_threads` parsing them.
+    pub fn new<R>(
+        csv_reader: csv::Reader<R>,
+        parser: ByteRecordParser,
+        parser_threads: NonZeroUsize,
+        chunk_len: NonZeroUsize,
+    ) -> Self
+    where
+        R: io::Read + Send + 'static,
+    {
+        let mut threads = vec![];
+        let mut raw_txs = vec![];
+        let mut parsed_rxs = vec![];
+        let (recycled_tx, recycled_rx) = sync_channel(parser_threads.get() * CHANNEL_CAPACITY);
+        for _ in 0..parser_threads.get() {
+            let (raw_tx, raw_rx) = sync_channel(CHANNEL_CAPACITY);
+            let (parsed_tx, parsed_rx) = sync_channel(CHANNEL_CAPACITY);
+            let parser = parser.clone();
+            let recycled_tx = recycled_tx.clone();
+            threads.push(thread::spawn(move || {
+                parse_chunks(&parser, raw_rx, parsed_tx, recycled_tx)
+            }));
+            raw_txs.push(raw_tx);
+            parsed_rxs.push(parsed_rx);
+        }
+        threads.push(thread::spawn(move || {
+            read_chunks(csv_reader, chunk_len.get(), raw_txs, recycled_rx)
+        }));
+
+        Self {
+            parsed_rxs,
+            next_rx_idx: 0,
+            rows: Vec::new().into_iter(),
+            read_error_opt: None,
+            threads,
+            done: false,
+        }
+    }
+
+    /// Wait for the threads; a panic in any of them is propagated.
+    fn join(&mut self) {
+        for thread in self.threads.drain(..) {
+            if let Err(panic) = thread.join() {
+                std::panic::resume_unwind(panic);
+            }
+        }
+    }
+}
+
+impl Iterator for PipelinedRows {
+    type Item = Result<Row, csv::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(row) = self.rows.next() {
+                return Some(Ok(row));
+            }
+            if let Some(read_error) = self.read_error_opt.take() {
+                self.done = true;
+                return Some(Err(read_error));
+            }
+            if self.done {
+                return None;
+            }
+            // the chunks are dealt to the threads in turn: so are they
+            // collected.
+            let Ok(ParsedChunk {
+                rows,
+                read_error_opt,
+            }) = self.parsed_rxs[self.next_rx_idx].recv()
+            else {
+                self.done = true;
+                self.join();
+                return None;
+            };
+            self.next_rx_idx = (self.next_rx_idx + 1) % self.parsed_rxs.len();
+            self.rows = rows.into_iter();
+            self.read_error_opt = read_error_opt;
+        }
+    }
+}
+
+fn read_chunks<R: io::Read>(
+    mut csv_reader: csv::Reader<R>,
+    chunk_len: usize,
+    raw_txs: Vec<SyncSender<RawChunk>>,
+    recycled_rx: Receiver<Vec<ByteRecord>>,
+) {
+    let mut first_row_idx = 0;
+    for raw_tx in raw_txs.iter().cycle() {
+        // the records parsed are sent back to be read into again.
+        let mut records = recycled_rx
+            .try_recv()
+            .unwrap_or_else(|_| Vec::with_capacity(chunk_len));
+        let mut records_count = 0;
+        let mut read_error_opt = None;
+        while records_count < chunk_len {
+            if records.len() == records_count {
+                records.push(ByteRecord::new());
+            }
+            match csv_reader.read_byte_record(&mut records[records_count]) {
+                Ok(true) => records_count += 1,
+                Ok(false) => break,
+                Err(reason) => {
+                    read_error_opt = Some(reason);
+                    break;
+                }
+            }
+        }
+        records.truncate(records_count);
+
+        let is_last = records_count < chunk_len || read_error_opt.is_some();
+        let chunk = RawChunk {
+            first_row_idx,
+            records,
+            read_error_opt,
+        };
+        if raw_tx.send(chunk).is_err() || is_last {
+            return;
+        }
+        first_row_idx += records_count;
+    }
+}
+
+fn parse_chunks(
+    parser: &ByteRecordParser,
+    raw_rx: Receiver<RawChunk>,
+    parsed_tx: SyncSender<ParsedChunk>,
+    recycled_tx: SyncSender<Vec<ByteRecord>>,
+) {
+    for RawChunk {
+        first_row_idx,
+        records,
+        read_error_opt,
+    } in raw_rx
+    {
+        let rows = records
+            .iter()
+            .enumerate()
+            .map(|(idx, record)| (first_row_idx + idx, parser.parse(record)))
+            .collect();
+        let _ = recycled_tx.try_send(records);
+        let chunk = ParsedChunk {
+            rows,
+            read_error_opt,
+        };
+        if parsed_tx.send(chunk).is_err() {
+            return;
+        }
+    }
+}
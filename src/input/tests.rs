@@ -1,33 +1,354 @@
-use std::path::Path;
+use std::{
+    fs, io,
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+};
 
 use test_case::test_case;
 
-use crate::input::Tx;
+use crate::{
+    input::{
+        ByteRecordParser, PipelinedRows, Rows, Tx,
+        binary::{BinaryRows, BinaryWriter, MAGIC, RECORD_LEN, VERSION},
+        config::InputConfig,
+        jsonl::JsonlRows,
+        precision::RoundingCounter,
+    },
+    types::Rounding,
+};
 
 #[test_case("deposits")]
 #[test_case("withdrawals")]
+#[test_case("transfers")]
 #[test_case("disputes")]
 #[test_case("resolves")]
 #[test_case("chargebacks")]
+#[test_case("timestamps")]
 fn parse_csv(case_name: &str) {
-    let input_file = Path::new(file!())
+    let mut output = vec![];
+    let csv_reader = csv_reader(&format!("{}.csv", case_name));
+    for decode_result in csv_reader.into_deserialize::<Tx>() {
+        output.push(decode_result.map_err(|e| e.to_string()));
+    }
+    assert_snapshot(&snapshot_name(case_name), output);
+}
+
+/// The fast path yields the same as the serde path: the snapshots are shared.
+#[test_case("deposits")]
+#[test_case("withdrawals")]
+#[test_case("transfers")]
+#[test_case("disputes")]
+#[test_case("resolves")]
+#[test_case("chargebacks")]
+#[test_case("timestamps")]
+fn parse_csv_byte_records(case_name: &str) {
+    let mut output = vec![];
+    let mut csv_reader = csv_reader(&format!("{}.csv", case_name));
+    let parser = ByteRecordParser::new(csv_reader.byte_headers().expect("byte_headers"));
+    let mut record = csv::ByteRecord::new();
+    while csv_reader
+        .read_byte_record(&mut record)
+        .expect("read_byte_record")
+    {
+        output.push(parser.parse(&record).map_err(|e| e.to_string()));
+    }
+    assert_snapshot(&snapshot_name(case_name), output);
+}
+
+/// The rows parsed in chunks by several threads are yielded in order.
+#[test_case("deposits")]
+#[test_case("withdrawals")]
+#[test_case("transfers")]
+#[test_case("disputes")]
+#[test_case("resolves")]
+#[test_case("chargebacks")]
+#[test_case("timestamps")]
+fn parse_csv_pipelined(case_name: &str) {
+    let mut output = vec![];
+    let mut csv_reader = csv_reader(&format!("{}.csv", case_name));
+    let parser = ByteRecordParser::new(csv_reader.byte_headers().expect("byte_headers"));
+    let rows = PipelinedRows::new(
+        csv_reader,
+        parser,
+        NonZeroUsize::new(3).expect("positive"),
+        NonZeroUsize::new(2).expect("positive"),
+    );
+    for (expected_row_idx, row) in rows.enumerate() {
+        let (row_idx, parse_result) = row.expect("read row");
+        assert_eq!(row_idx, expected_row_idx);
+        output.push(parse_result.map_err(|e| e.to_string()));
+    }
+    assert_snapshot(&snapshot_name(case_name), output);
+}
+
+/// In the strict mode, the rows are checked against the header.
+#[test_case("strict")]
+fn parse_csv_strict(case_name: &str) {
+    let config = InputConfig {
+        strict: true,
+        ..InputConfig::default()
+    };
+    let (output, _) = read_rows(&format!("{}.csv", case_name), &config);
+    assert_snapshot(case_name, output);
+}
+
+#[test_case("type,client,tx,amount,to_client,timestamp", None)]
+#[test_case("type,client,tx,amount", None)]
+#[test_case("type,client,tx", Some("invalid header: column `amount` is missing"))]
+#[test_case(
+    "type,client,tx,amount,memo",
+    Some("invalid header: field 4: unexpected column `memo`")
+)]
+#[test_case(
+    "type,client,tx,amount,tx",
+    Some("invalid header: field 4: duplicate column `tx`")
+)]
+fn strict_header(header: &str, expected_error_opt: Option<&str>) {
+    let headers = csv::ByteRecord::from(header.split(',').collect::<Vec<_>>());
+    let error_opt = ByteRecordParser::strict(&headers)
+        .err()
+        .map(|e| e.to_string());
+    assert_eq!(error_opt.as_deref(), expected_error_opt);
+}
+
+/// The names of the columns and of the types are mapped, and the amounts
+/// rewritten, by the config.
+#[test_case("partner", "csv")]
+#[test_case("partner", "jsonl")]
+#[test_case("european", "csv")]
+#[test_case("european", "jsonl")]
+fn parse_mapped(case_name: &str, extension: &str) {
+    let config_file = Path::new(file!())
         .parent()
         .expect("file!().parent")
+        .join("config")
         .join("cases")
-        .join(format!("{}.csv", case_name));
-    let mut output = vec![];
-    let csv_reader = csv::ReaderBuilder::new()
+        .join(format!("{}.toml", case_name));
+    let config = toml::from_str::<InputConfig>(
+        &fs::read_to_string(config_file).expect("fs::read_to_string"),
+    )
+    .expect("toml::from_str");
+    let (output, _) = read_rows(&format!("{}.{}", case_name, extension), &config);
+    assert_snapshot(&format!("{}-{}", case_name, extension), output);
+}
+
+/// The too precise amounts are rounded (and counted) as configured.
+#[test_case(Rounding::HalfEven, "csv")]
+#[test_case(Rounding::HalfEven, "jsonl")]
+#[test_case(Rounding::HalfUp, "csv")]
+#[test_case(Rounding::HalfUp, "jsonl")]
+#[test_case(Rounding::Truncate, "csv")]
+#[test_case(Rounding::Truncate, "jsonl")]
+fn parse_rounded(rounding: Rounding, extension: &str) {
+    let config = InputConfig {
+        amount_rounding: Some(rounding),
+        ..InputConfig::default()
+    };
+    let (output, rounding_counter) = read_rows(&format!("rounding.{}", extension), &config);
+    let output = output
+        .into_iter()
+        .map(|parse_result| parse_result.map(|tx| format!("{:?}", tx.kind)))
+        .collect::<Vec<_>>();
+    assert_snapshot(
+        &format!("rounding-{}-{:?}", extension, rounding),
+        (output, rounding_counter.stats()),
+    );
+}
+
+/// The amounts are taken exactly, whether strings or numbers.
+#[test_case("json-lines")]
+fn parse_jsonl(case_name: &str) {
+    let (output, _) = read_rows(&format!("{}.jsonl", case_name), &InputConfig::default());
+    assert_snapshot(&snapshot_name(case_name), output);
+}
+
+/// The name of the case's snapshot, suffixed with the features widening the
+/// ids its rows are parsed differently with (the ids out of the default range
+/// are taken, rather than rejected).
+fn snapshot_name(case_name: &str) -> String {
+    const WIDE_CLIENT_IDS_CASES: &[&str] = &[
+        "transfers",
+        "disputes",
+        "resolves",
+        "chargebacks",
+        "json-lines",
+    ];
+    const WIDE_TX_IDS_CASES: &[&str] = &["disputes", "resolves", "chargebacks"];
+
+    let mut snapshot_name = case_name.to_owned();
+    if cfg!(feature = "wide-client-ids") && WIDE_CLIENT_IDS_CASES.contains(&case_name) {
+        snapshot_name.push_str(".wide-client-ids");
+    }
+    if cfg!(feature = "wide-tx-ids") && WIDE_TX_IDS_CASES.contains(&case_name) {
+        snapshot_name.push_str(".wide-tx-ids");
+    }
+    snapshot_name
+}
+
+/// The case file, read as CSV.
+fn csv_reader(file_name: &str) -> csv::Reader<fs::File> {
+    csv::ReaderBuilder::new()
         .flexible(true)
         .trim(csv::Trim::All)
-        .from_path(input_file)
-        .expect("CsvReader::from_path");
-    for decode_result in csv_reader.into_deserialize::<Tx>() {
-        output.push(decode_result.map_err(|e| e.to_string()));
-    }
+        .from_path(case_file(file_name))
+        .expect("CsvReader::from_path")
+}
+
+/// The rows of the case file (CSV, or JSON Lines by the extension), parsed
+/// with the config; and the counter of the amounts rounded.
+fn read_rows(file_name: &str, config: &InputConfig) -> (Vec<Result<Tx, String>>, RoundingCounter) {
+    let mut output = vec![];
+    let rounding_counter = if file_name.ends_with(".csv") {
+        let mut csv_reader = csv_reader(file_name);
+        let parser =
+            ByteRecordParser::with_config(csv_reader.byte_headers().expect("byte_headers"), config)
+                .expect("ByteRecordParser::with_config");
+        let rounding_counter = parser.rounding_counter();
+        for (expected_row_idx, row) in Rows::new(csv_reader, parser).enumerate() {
+            let (row_idx, parse_result) = row.expect("read row");
+            assert_eq!(row_idx, expected_row_idx);
+            output.push(parse_result.map_err(|e| e.to_string()));
+        }
+        rounding_counter
+    } else {
+        let reader = io::BufReader::new(fs::File::open(case_file(file_name)).expect("File::open"));
+        let rows = JsonlRows::with_config(reader, config);
+        let rounding_counter = rows.rounding_counter();
+        for (expected_row_idx, row) in rows.enumerate() {
+            let (row_idx, parse_result) = row.expect("read line");
+            assert_eq!(row_idx, expected_row_idx);
+            output.push(parse_result.map_err(|e| e.to_string()));
+        }
+        rounding_counter
+    };
+    (output, rounding_counter)
+}
+
+/// The path of the case file.
+fn case_file(file_name: &str) -> PathBuf {
+    Path::new(file!())
+        .parent()
+        .expect("file!().parent")
+        .join("cases")
+        .join(file_name)
+}
+
+/// Snapshot the output into `cases`, under the name given.
+fn assert_snapshot(snapshot_name: &str, output: impl std::fmt::Debug) {
     insta::with_settings!({
         snapshot_path => "cases",
         prepend_module_to_snapshot => false,
     }, {
-        insta::assert_debug_snapshot!(case_name, output);
+        insta::assert_debug_snapshot!(snapshot_name, output);
     });
 }
+
+/// The transactions written in the binary format are read back the same.
+#[test_case("deposits")]
+#[test_case("withdrawals")]
+#[test_case("transfers")]
+#[test_case("disputes")]
+#[test_case("resolves")]
+#[test_case("chargebacks")]
+#[test_case("timestamps")]
+fn binary_round_trip(case_name: &str) {
+    let transactions = csv_reader(&format!("{}.csv", case_name))
+        .into_deserialize::<Tx>()
+        .filter_map(Result::ok)
+        .collect::<Vec<_>>();
+
+    let mut binary_writer = BinaryWriter::new(vec![]).expect("BinaryWriter::new");
+    for tx in &transactions {
+        binary_writer.write(tx).expect("BinaryWriter::write");
+    }
+    let binary = binary_writer
+        .into_inner()
+        .expect("BinaryWriter::into_inner");
+
+    let mut read_back = vec![];
+    for (expected_row_idx, row) in BinaryRows::new(binary.as_slice())
+        .expect("BinaryRows::new")
+        .enumerate()
+    {
+        let (row_idx, decode_result) = row.expect("read record");
+        assert_eq!(row_idx, expected_row_idx);
+        read_back.push(decode_result.expect("decode record"));
+    }
+    assert_eq!(format!("{:?}", read_back), format!("{:?}", transactions));
+}
+
+#[test]
+fn binary_malformed() {
+    let mut binary = [&MAGIC[..], &[VERSION]].concat();
+    binary.extend([0; RECORD_LEN]); // a deposit of zero
+    binary.extend([9; RECORD_LEN]); // an unknown kind
+    binary.extend([3; RECORD_LEN - 1]); // truncated
+    let output = BinaryRows::new(binary.as_slice())
+        .expect("BinaryRows::new")
+        .map(|row| match row {
+            Ok((row_idx, decode_result)) => {
+                format!(
+                    "{}: {:?}",
+                    row_idx,
+                    decode_result.map_err(|e| e.to_string())
+                )
+            }
+            Err(reason) => format!("read error: {}", reason),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        output,
+        [
+            "0: Err(\"invalid transaction record: expected positive amount; got: 0.0\")",
+            "1: Err(\"invalid transaction record: unknown kind: 9\")",
+            "read error: truncated transaction record",
+        ]
+    );
+
+    let error = BinaryRows::new(&b"TXS\x02"[..]).expect_err("version 2");
+    assert_eq!(error.to_string(), "unsupported binary format version: 2");
+}
+
+/// The ids beyond the default widths are taken only with the features
+/// widening them: by either parser, and through the binary format.
+#[test_case("deposit,65536,1,1.0", cfg!(feature = "wide-client-ids"))]
+#[test_case("transfer,1,1,1.0,65536", cfg!(feature = "wide-client-ids"))]
+#[test_case("deposit,1,4294967296,1.0", cfg!(feature = "wide-tx-ids"))]
+#[test_case("deposit,65535,4294967295,1.0", true)]
+fn wide_ids(row: &str, expected_ok: bool) {
+    let input = format!("type,client,tx,amount,to_client\n{}\n", row);
+    let mut csv_reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(input.as_bytes());
+    let headers = csv_reader.byte_headers().expect("byte_headers").clone();
+    let parser = ByteRecordParser::new(&headers);
+    let record = csv_reader
+        .byte_records()
+        .next()
+        .expect("a row")
+        .expect("read row");
+    let parse_result = parser.parse(&record);
+    assert_eq!(parse_result.is_ok(), expected_ok, "{:?}", parse_result);
+
+    let deserialize_result = record.deserialize::<Tx>(Some(&headers));
+    assert_eq!(deserialize_result.is_ok(), expected_ok);
+
+    let Ok(tx) = parse_result else {
+        return;
+    };
+    let mut binary_writer = BinaryWriter::new(vec![]).expect("BinaryWriter::new");
+    binary_writer.write(&tx).expect("BinaryWriter::write");
+    let binary = binary_writer
+        .into_inner()
+        .expect("BinaryWriter::into_inner");
+    assert_eq!(binary.len(), MAGIC.len() + 1 + RECORD_LEN);
+    let (_, decode_result) = BinaryRows::new(binary.as_slice())
+        .expect("BinaryRows::new")
+        .next()
+        .expect("a record")
+        .expect("read record");
+    assert_eq!(
+        format!("{:?}", decode_result.expect("decode record")),
+        format!("{:?}", tx)
+    );
+}
@@ -5,4 +5,5 @@
 pub mod engine;
 pub mod input;
 pub mod output;
+pub mod reorder;
 pub mod types;
@@ -1,9 +1,37 @@
-use std::{env, error, io, process};
+use std::{env, error, fs, io, num::NonZeroUsize, path::Path, process};
 
-use balances::{engine::Engine, input::Tx};
+use balances::{
+    engine::{
+        Engine,
+        config::{BalanceStoreConfig, EngineConfig},
+    },
+    input::{
+        ByteRecordParser, CreditLimit, PipelinedRows, Rows, Tx,
+        binary::{BinaryRows, BinaryWriter},
+        config::InputConfig,
+        jsonl::JsonlRows,
+        precision::RoundingCounter,
+    },
+    output::TxOutcome,
+    reorder::ReorderBuffer,
+};
 
 type AnyError = Box<dyn error::Error + Send + Sync + 'static>;
 
+/// The rows of the input, whatever its format.
+type AnyRows = Box<dyn Iterator<Item = Result<(usize, Result<Tx, AnyError>), AnyError>>>;
+
+/// The format of the input: given by the `--format` flag, or else by the
+/// extension of the file (CSV, unless recognised).
+#[derive(Debug, Clone, Copy)]
+enum InputFormat {
+    Csv,
+    Jsonl,
+    Binary,
+}
+
+const PARSER_CHUNK_LEN: NonZeroUsize = NonZeroUsize::new(1024).expect("positive");
+
 fn main() {
     if let Err(reason) = run() {
         eprintln!("FATAL: {}", reason);
@@ -12,38 +40,210 @@ fn main() {
 }
 
 fn run() -> Result<(), AnyError> {
-    let mut engine = if let Ok(tx_lru_size) = env::var("TX_LRU_SIZE") {
-        Engine::with_tx_cache_size(tx_lru_size.parse()?)
+    let mut args = env::args().skip(1).collect::<Vec<_>>();
+    let mut format_opt = None;
+    if let Some(flag_idx) = args.iter().position(|arg| arg == "--format") {
+        let Some(format_name) = args.get(flag_idx + 1) else {
+            return Err("--format: the format expected (csv, jsonl or binary)".into());
+        };
+        format_opt = Some(InputFormat::from_name(format_name)?);
+        args.drain(flag_idx..flag_idx + 2);
+    }
+
+    let mut input_config = if let Ok(config_path) = env::var("INPUT_CONFIG") {
+        toml::from_str(&fs::read_to_string(config_path)?)?
     } else {
-        Engine::default()
+        InputConfig::default()
     };
 
+    if env::var("STRICT_INPUT_ENABLED").is_ok_and(|v| v == "1") {
+        input_config.strict = true;
+    }
+
+    eprintln!("input config:\n{}", input_config);
+
+    input_config.validate()?;
+
+    match args.as_slice() {
+        [command, input, output] if command == "convert" => {
+            convert(input, output, format_opt, &input_config)
+        }
+        [input] => process(input, format_opt, &input_config),
+        _ => Err(
+            "exactly one argument expected (or: convert <input> <output.bin>); \
+             optionally, --format <csv|jsonl|binary>"
+                .into(),
+        ),
+    }
+}
+
+/// Convert the input file into the binary format.
+fn convert(
+    input: &str,
+    output: &str,
+    format_opt: Option<InputFormat>,
+    input_config: &InputConfig,
+) -> Result<(), AnyError> {
+    eprintln!("converting {} into {}...", input, output);
+
+    let input_format = format_opt.unwrap_or_else(|| InputFormat::of_path(input));
+    let (rows, rounding_counter) = input_format.open(input, None, input_config)?;
+    let mut binary_writer = BinaryWriter::new(io::BufWriter::new(fs::File::create(output)?))?;
+    let mut converted_count = 0;
+    for row in rows {
+        let (row_idx, parse_result) = row?;
+        match parse_result {
+            Ok(tx) => {
+                binary_writer.write(&tx)?;
+                converted_count += 1;
+            }
+            Err(reason) => eprintln!(
+                "[{}] {} deserialize error: {}",
+                row_idx,
+                input_format.name(),
+                reason
+            ),
+        }
+    }
+    binary_writer.into_inner()?;
+
+    eprintln!("converted {} rows", converted_count);
+    report_rounding(input_config, &rounding_counter);
+    Ok(())
+}
+
+/// Process the input file, and print the accounts.
+fn process(
+    input: &str,
+    format_opt: Option<InputFormat>,
+    input_config: &InputConfig,
+) -> Result<(), AnyError> {
+    let mut config = if let Ok(config_path) = env::var("ENGINE_CONFIG") {
+        toml::from_str(&fs::read_to_string(config_path)?)?
+    } else {
+        EngineConfig::default()
+    };
+
+    if let Ok(tx_lru_size) = env::var("TX_LRU_SIZE") {
+        config.tx_cache_size = tx_lru_size.parse()?;
+    }
+
+    if let Ok(memory_budget) = env::var("MEMORY_BUDGET") {
+        config.memory_budget = Some(memory_budget.parse()?);
+    }
+
+    match env::var("BALANCE_STORE").as_deref() {
+        Err(_) => (),
+        Ok("hashmap") => config.balance_store = BalanceStoreConfig::HashMap,
+        Ok("dense") => config.balance_store = BalanceStoreConfig::Dense,
+        Ok(other) => {
+            let Some(path) = other.strip_prefix("file:") else {
+                return Err(format!("unknown balance store: {}", other).into());
+            };
+            config.balance_store = BalanceStoreConfig::File { path: path.into() };
+        }
+    }
+
     if env::var("ACCOUNT_PRUNING_ENABLED").is_ok_and(|v| v == "1") {
-        engine.set_account_pruning(true);
+        config.account_pruning = true;
+    }
+
+    if env::var("TX_ID_TRACKING_ENABLED").is_ok_and(|v| v == "1") {
+        config.tx_id_tracking = true;
+    }
+
+    if let Ok(tx_store_path) = env::var("TX_STORE_PATH") {
+        config.tx_store_path = Some(tx_store_path.into());
+    }
+
+    if let Ok(credit_limits) = env::var("CREDIT_LIMITS") {
+        let csv_reader = csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .from_path(credit_limits)?;
+        for credit_limit in csv_reader.into_deserialize::<CreditLimit>() {
+            config.credit_limits.push(credit_limit?);
+        }
     }
 
-    let Some(input) = env::args().nth(1) else {
-        return Err("exactly one argument expected".into());
+    eprintln!("engine config:\n{}", config);
+
+    let mut statements_writer_opt = match (
+        config.time_policy.statement_period_secs,
+        env::var("STATEMENTS_PATH"),
+    ) {
+        (None, _) => None,
+        (Some(_), Ok(statements_path)) => Some(csv::Writer::from_path(statements_path)?),
+        (Some(_), Err(_)) => {
+            return Err("statements are issued, but STATEMENTS_PATH is not set".into());
+        }
     };
 
+    let mut engine = config.build()?;
+
+    let mut reorder_buffer_opt = env::var("REORDER_WINDOW")
+        .ok()
+        .map(|window_secs| window_secs.parse())
+        .transpose()?
+        .map(ReorderBuffer::new);
+
+    let parser_threads_opt = env::var("PARSER_THREADS")
+        .ok()
+        .map(|parser_threads| parser_threads.parse::<usize>())
+        .transpose()?
+        .and_then(NonZeroUsize::new);
+
     eprintln!("processing {}...", input);
 
-    let csv_reader = csv::ReaderBuilder::new()
-        .trim(csv::Trim::All)
-        .flexible(true)
-        .from_path(input)?;
+    let input_format = format_opt.unwrap_or_else(|| InputFormat::of_path(input));
+    let (rows, rounding_counter) = input_format.open(input, parser_threads_opt, input_config)?;
 
-    for (row_idx, input_row) in csv_reader.into_deserialize::<Tx>().enumerate() {
-        let Ok(tx) =
-            input_row.inspect_err(|e| eprintln!("[{}] csv deserialize error: {}", row_idx, e))
-        else {
+    for row in rows {
+        let (row_idx, parse_result) = row?;
+        let Ok(tx) = parse_result.inspect_err(|e| {
+            eprintln!(
+                "[{}] {} deserialize error: {}",
+                row_idx,
+                input_format.name(),
+                e
+            )
+        }) else {
             continue;
         };
-        eprintln!("processing {:?}...", tx);
-        if let Err(reason) = engine.process_tx(tx) {
-            eprintln!("[{}] engine processing error: {}", row_idx, reason);
+        match reorder_buffer_opt.as_mut() {
+            None => process_row(&mut engine, row_idx, tx),
+            Some(reorder_buffer) => {
+                if let Err(reason) = reorder_buffer.push(row_idx, tx) {
+                    eprintln!("[{}] reorder error: {}", row_idx, reason);
+                }
+                while let Some((row_idx, tx)) = reorder_buffer.pop_ready() {
+                    process_row(&mut engine, row_idx, tx);
+                }
+            }
         }
+        write_statements(&mut engine, statements_writer_opt.as_mut())?;
     }
+    if let Some(reorder_buffer) = reorder_buffer_opt.as_mut() {
+        while let Some((row_idx, tx)) = reorder_buffer.pop() {
+            process_row(&mut engine, row_idx, tx);
+        }
+    }
+
+    engine.close_statement_period();
+    write_statements(&mut engine, statements_writer_opt.as_mut())?;
+    if let Some(statements_writer) = statements_writer_opt.as_mut() {
+        statements_writer.flush()?;
+    }
+
+    engine.flush()?;
+
+    report_rounding(input_config, &rounding_counter);
+
+    let memory_usage = engine.memory_usage();
+    eprintln!(
+        "memory usage: {} bytes ({:?})",
+        memory_usage.total(),
+        memory_usage
+    );
 
     let stdout = io::stdout().lock();
     let mut csv_writer = csv::WriterBuilder::new().from_writer(stdout);
@@ -54,3 +254,122 @@ fn run() -> Result<(), AnyError> {
 
     Ok(())
 }
+
+impl InputFormat {
+    fn from_name(name: &str) -> Result<Self, AnyError> {
+        match name {
+            "csv" => Ok(Self::Csv),
+            "jsonl" => Ok(Self::Jsonl),
+            "binary" => Ok(Self::Binary),
+            other => Err(format!("unknown input format: {}", other).into()),
+        }
+    }
+
+    fn of_path(path: &str) -> Self {
+        match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+            Some("jsonl" | "ndjson") => Self::Jsonl,
+            Some("bin") => Self::Binary,
+            _ => Self::Csv,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Jsonl => "jsonl",
+            Self::Binary => "binary",
+        }
+    }
+
+    /// Open the input file, and read its rows; the CSV-rows are parsed in the
+    /// background if the parser threads are given, and checked against the
+    /// header if strict; the names in them are mapped, and the amounts
+    /// rounded, according to the config (but the binary format has neither);
+    /// also the counter of the rounded rows.
+    fn open(
+        self,
+        path: &str,
+        parser_threads_opt: Option<NonZeroUsize>,
+        input_config: &InputConfig,
+    ) -> Result<(AnyRows, RoundingCounter), AnyError> {
+        let rows_and_counter = match self {
+            Self::Csv => {
+                let mut csv_reader = csv::ReaderBuilder::new()
+                    .trim(csv::Trim::All)
+                    .flexible(true)
+                    .from_path(path)?;
+                let headers = csv_reader.byte_headers()?;
+                let parser = ByteRecordParser::with_config(headers, input_config)?;
+                let rounding_counter = parser.rounding_counter();
+                let rows = match parser_threads_opt {
+                    None => any_rows(Rows::new(csv_reader, parser)),
+                    Some(parser_threads) => any_rows(PipelinedRows::new(
+                        csv_reader,
+                        parser,
+                        parser_threads,
+                        PARSER_CHUNK_LEN,
+                    )),
+                };
+                (rows, rounding_counter)
+            }
+            Self::Jsonl => {
+                let reader = io::BufReader::new(fs::File::open(path)?);
+                let rows = JsonlRows::with_config(reader, input_config);
+                let rounding_counter = rows.rounding_counter();
+                (any_rows(rows), rounding_counter)
+            }
+            Self::Binary => (
+                any_rows(BinaryRows::new(io::BufReader::new(fs::File::open(path)?))?),
+                RoundingCounter::default(),
+            ),
+        };
+        Ok(rows_and_counter)
+    }
+}
+
+fn any_rows<I, E, F>(rows: I) -> AnyRows
+where
+    I: Iterator<Item = Result<(usize, Result<Tx, F>), E>> + 'static,
+    E: Into<AnyError>,
+    F: Into<AnyError>,
+{
+    Box::new(rows.map(|row| {
+        let (row_idx, parse_result) = row.map_err(Into::into)?;
+        Ok((row_idx, parse_result.map_err(Into::into)))
+    }))
+}
+
+/// Report the rows the amounts of which were rounded, if rounding at all.
+fn report_rounding(input_config: &InputConfig, rounding_counter: &RoundingCounter) {
+    if input_config.amount_rounding.is_some() {
+        let stats = rounding_counter.stats();
+        eprintln!(
+            "amounts rounded: {} rows, adjusted by {} in total",
+            stats.rounded_rows, stats.adjustment
+        );
+    }
+}
+
+/// Write out the statements issued so far (if any): the engine keeps none.
+fn write_statements(
+    engine: &mut Engine,
+    statements_writer_opt: Option<&mut csv::Writer<fs::File>>,
+) -> Result<(), AnyError> {
+    let Some(statements_writer) = statements_writer_opt else {
+        return Ok(());
+    };
+    for statement in engine.take_statements() {
+        statements_writer.serialize(statement)?;
+    }
+    Ok(())
+}
+
+fn process_row(engine: &mut Engine, row_idx: usize, tx: Tx) {
+    eprintln!("processing {:?}...", tx);
+    let tx_id = tx.tx_id;
+    match engine.process_tx(tx) {
+        Ok(TxOutcome::Applied) => (),
+        Ok(TxOutcome::Duplicate) => eprintln!("[{}] duplicate ignored: {}", row_idx, tx_id),
+        Err(reason) => eprintln!("[{}] engine processing error: {}", row_idx, reason),
+    }
+}
@@ -1,6 +1,6 @@
 //! This module contains types necessary to render the program's output.
 
-use crate::types::{Amount, ClientId, NonNegativeAmount};
+use crate::types::{Amount, ClientId, NonNegativeAmount, PositiveAmount, Timestamp, TxId};
 
 /// A serde-serializable account entry
 #[derive(Debug, Clone, serde::Serialize)]
@@ -17,4 +17,82 @@ pub struct Account {
     /// shows whether the account is locked.
     #[serde(rename = "locked")]
     pub is_locked: bool,
+    /// how far below zero the available funds may go.
+    pub credit_limit: NonNegativeAmount,
+    /// the part of the credit line currently drawn.
+    pub credit_used: NonNegativeAmount,
+    /// shows whether the available funds went below the credit line.
+    #[serde(rename = "in_debt")]
+    pub is_in_debt: bool,
+}
+
+/// What became of a transaction that has been processed without an error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TxOutcome {
+    /// the transaction has been applied.
+    Applied,
+    /// the very same transaction has already been applied; nothing changed.
+    Duplicate,
+}
+
+/// A serde-serializable transaction entry
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Transaction {
+    /// tx-id
+    #[serde(rename = "tx")]
+    pub tx_id: TxId,
+    /// client-id (the sender, if the transaction is a transfer).
+    #[serde(rename = "client")]
+    pub client_id: ClientId,
+    /// client-id of the receiver, if the transaction is a transfer.
+    #[serde(rename = "to_client")]
+    pub to_client_id: Option<ClientId>,
+    /// amount of the transaction; the amount held, if it is under dispute.
+    pub amount: PositiveAmount,
+    /// the state the transaction is in.
+    pub state: TransactionState,
+    /// how many times the transaction has been disputed.
+    pub dispute_count: u32,
+}
+
+/// The state of a transaction kept by the engine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransactionState {
+    /// the deposit can be disputed.
+    Deposited,
+    /// the withdrawal is only kept to recognise the reused tx-ids.
+    Withdrawn,
+    /// the deposit is under dispute.
+    Disputed,
+}
+
+/// A serde-serializable summary of an account's activity over a statement
+/// period.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Statement {
+    /// client-id
+    #[serde(rename = "client")]
+    pub client_id: ClientId,
+    /// the beginning of the period (inclusive).
+    pub period_start: Timestamp,
+    /// the end of the period (exclusive).
+    pub period_end: Timestamp,
+    /// total funds at the beginning of the period.
+    pub opening_total: Amount,
+    /// funds deposited during the period.
+    pub deposited: NonNegativeAmount,
+    /// funds withdrawn during the period.
+    pub withdrawn: NonNegativeAmount,
+    /// funds received from other clients during the period.
+    pub transferred_in: NonNegativeAmount,
+    /// funds sent to other clients during the period.
+    pub transferred_out: NonNegativeAmount,
+    /// funds returned by the charged back outgoing transfers during the period.
+    pub transfers_reversed: NonNegativeAmount,
+    /// funds charged back during the period.
+    pub chargedback: NonNegativeAmount,
+    /// total funds at the end of the period.
+    pub closing_total: Amount,
 }
@@ -0,0 +1,116 @@
+//! Restoring the order of transactions that arrive slightly out of order.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use crate::{
+    input::Tx,
+    types::{ClientId, Timestamp},
+};
+
+/// Buffers the timestamped transactions, releasing them in the order of their
+/// timestamps once they are older than the window relative to the latest
+/// timestamp seen.
+///
+/// Every transaction is accompanied with a `row` (e.g. its position in the
+/// input) to be released with it. A transaction without a timestamp releases
+/// everything buffered before it, and then itself.
+#[derive(Debug)]
+pub struct ReorderBuffer<R> {
+    window_secs: u64,
+    latest_opt: Option<Timestamp>,
+    seq_no: u64,
+    pending: BTreeMap<(Timestamp, u64), (R, Tx)>,
+    ready: VecDeque<(R, Tx)>,
+    released: HashMap<ClientId, Timestamp>,
+}
+
+/// The transaction's client has already had a later transaction released, so
+/// it cannot be put in order anymore.
+#[derive(Debug, thiserror::Error)]
+#[error("too late to reorder: {} at {} after {}", tx.client_id, timestamp, released)]
+pub struct TooLate {
+    /// The rejected transaction.
+    pub tx: Tx,
+    /// The rejected transaction's timestamp.
+    pub timestamp: Timestamp,
+    /// The latest timestamp released for the client.
+    pub released: Timestamp,
+}
+
+impl<R> ReorderBuffer<R> {
+    /// Create a buffer holding the transactions for `window_secs`.
+    pub fn new(window_secs: u64) -> Self {
+        Self {
+            window_secs,
+            latest_opt: None,
+            seq_no: 0,
+            pending: Default::default(),
+            ready: Default::default(),
+            released: Default::default(),
+        }
+    }
+
+    /// Add a transaction to the buffer.
+    pub fn push(&mut self, row: R, tx: Tx) -> Result<(), TooLate> {
+        let Some(timestamp) = tx.timestamp else {
+            while let Some((_, entry)) = self.pending.pop_first() {
+                self.release(entry);
+            }
+            self.ready.push_back((row, tx));
+            return Ok(());
+        };
+
+        if let Some(&released) = self.released.get(&tx.client_id)
+            && timestamp < released
+        {
+            return Err(TooLate {
+                tx,
+                timestamp,
+                released,
+            });
+        }
+
+        self.latest_opt = self.latest_opt.max(Some(timestamp));
+        self.seq_no += 1;
+        self.pending.insert((timestamp, self.seq_no), (row, tx));
+
+        Ok(())
+    }
+
+    /// Take the next transaction that is due to be processed.
+    pub fn pop_ready(&mut self) -> Option<(R, Tx)> {
+        if self.ready.is_empty() {
+            let latest = self.latest_opt?;
+            let entry = self.pending.first_entry()?;
+            let &(timestamp, _) = entry.key();
+            if latest.secs_since(timestamp) < self.window_secs {
+                return None;
+            }
+            let entry = entry.remove();
+            self.release(entry);
+        }
+        self.ready.pop_front()
+    }
+
+    /// Take the next transaction regardless of the window (e.g. once the input
+    /// is exhausted).
+    pub fn pop(&mut self) -> Option<(R, Tx)> {
+        if self.ready.is_empty() {
+            let (_, entry) = self.pending.pop_first()?;
+            self.release(entry);
+        }
+        self.ready.pop_front()
+    }
+
+    fn release(&mut self, entry: (R, Tx)) {
+        let (_, tx) = &entry;
+        if let Some(timestamp) = tx.timestamp {
+            let released = self.released.entry(tx.client_id).or_insert(timestamp);
+            *released = (*released).max(timestamp);
+        }
+        self.ready.push_back(entry);
+    }
+}
+
+#[cfg(test)]
+mod tests;
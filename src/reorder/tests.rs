@@ -0,0 +1,59 @@
+use test_case::test_case;
+
+use crate::{
+    input::{Tx, TxKind},
+    reorder::ReorderBuffer,
+};
+
+#[test_case(0, [(1, Some(20)), (1, Some(10)), (2, None)]; "no-window")]
+#[test_case(10, [
+    (1, Some(20)),
+    (1, Some(15)),
+    (2, Some(5)),
+    (1, Some(31)),
+    (1, Some(18)),
+    (2, Some(12)),
+    (1, Some(25)),
+]; "window")]
+#[test_case(10, [
+    (1, Some(20)),
+    (2, Some(15)),
+    (1, None),
+    (2, Some(16)),
+    (2, Some(14)),
+]; "untimestamped")]
+fn reorder(window_secs: u64, transactions: impl IntoIterator<Item = (u16, Option<u64>)>) {
+    let case_name = std::thread::current()
+        .name()
+        .unwrap()
+        .to_owned()
+        .replace("::", "-");
+
+    let mut buffer = ReorderBuffer::new(window_secs);
+    let mut transcript = vec![];
+    for (row_idx, (client_id, timestamp_opt)) in transactions.into_iter().enumerate() {
+        let tx = Tx {
+            client_id: client_id.into(),
+            tx_id: (row_idx as u32).into(),
+            kind: TxKind::Dispute,
+            timestamp: timestamp_opt.map(Into::into),
+        };
+        let outcome = buffer.push(row_idx, tx).map_err(|e| e.to_string());
+        let mut released = vec![];
+        while let Some((row_idx, _)) = buffer.pop_ready() {
+            released.push(row_idx);
+        }
+        transcript.push((row_idx, outcome, released));
+    }
+    let mut flushed = vec![];
+    while let Some((row_idx, _)) = buffer.pop() {
+        flushed.push(row_idx);
+    }
+
+    insta::with_settings!({
+        snapshot_path => "cases",
+        prepend_module_to_snapshot => false,
+    }, {
+        insta::assert_yaml_snapshot!(case_name, (transcript, flushed));
+    });
+}
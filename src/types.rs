@@ -2,6 +2,22 @@
 
 use fixnum::{FixedPoint, typenum};
 
+/// The integer a [`ClientId`] is: `u16`; `u32` with the feature
+/// `wide-client-ids`.
+#[cfg(not(feature = "wide-client-ids"))]
+pub type ClientIdRepr = u16;
+/// The integer a [`ClientId`] is: `u16`; `u32` with the feature
+/// `wide-client-ids`.
+#[cfg(feature = "wide-client-ids")]
+pub type ClientIdRepr = u32;
+
+/// The integer a [`TxId`] is: `u32`; `u64` with the feature `wide-tx-ids`.
+#[cfg(not(feature = "wide-tx-ids"))]
+pub type TxIdRepr = u32;
+/// The integer a [`TxId`] is: `u32`; `u64` with the feature `wide-tx-ids`.
+#[cfg(feature = "wide-tx-ids")]
+pub type TxIdRepr = u64;
+
 /// Client ID
 #[derive(
     Debug,
@@ -15,7 +31,7 @@ use fixnum::{FixedPoint, typenum};
     serde::Serialize,
     serde::Deserialize
 )]
-pub struct ClientId(u16);
+pub struct ClientId(ClientIdRepr);
 
 /// Transaction ID
 #[derive(
@@ -30,12 +46,51 @@ pub struct ClientId(u16);
     serde::Serialize,
     serde::Deserialize
 )]
-pub struct TxId(u32);
+pub struct TxId(TxIdRepr);
+
+/// Point in time: seconds since the Unix epoch.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    serde::Serialize,
+    serde::Deserialize
+)]
+pub struct Timestamp(u64);
 
 /// Fixed point number to keep amounts: precision — 4 digits past the decimal
 /// point.
 pub type Amount = FixedPoint<i128, typenum::U4>;
 
+/// Fixed point number to keep the amounts given more precisely than an
+/// [`Amount`] is kept: precision — 18 digits past the decimal point.
+pub type PreciseAmount = FixedPoint<i128, typenum::U18>;
+
+/// How to round a result that does not fit the [`Amount`]'s precision.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Rounding {
+    /// Round to the nearest; ties — to the even neighbour (banker's rounding).
+    HalfEven,
+    /// Round to the nearest; ties — away from zero.
+    HalfUp,
+    /// Round towards zero.
+    Truncate,
+}
+
 /// Amount that can only be positive.
 #[derive(
     Debug,
@@ -64,13 +119,21 @@ pub struct PositiveAmount(
     PartialOrd,
     Ord,
     Hash,
-    serde::Serialize
+    serde::Serialize,
+    serde::Deserialize
 )]
-pub struct NonNegativeAmount(Amount);
+pub struct NonNegativeAmount(
+    #[serde(
+        deserialize_with = "non_negative_amount::deserialize_amount_ensure_non_negative_value"
+    )]
+    Amount,
+);
 
 mod non_negative_amount {
     use std::fmt;
 
+    use serde::{Deserializer, de::Visitor};
+
     use super::*;
 
     #[derive(Debug, thiserror::Error)]
@@ -101,6 +164,35 @@ mod non_negative_amount {
             fmt::Display::fmt(&self.0, f)
         }
     }
+
+    pub(super) fn deserialize_amount_ensure_non_negative_value<'de, D>(
+        deserializer: D,
+    ) -> Result<Amount, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        // not necessarily borrowed: the amounts in config-files are unescaped.
+        struct AmountVisitor;
+
+        impl Visitor<'_> for AmountVisitor {
+            type Value = Amount;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a string containing a non-negative amount")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, s: &str) -> Result<Amount, E> {
+                let a = Amount::from_str_exact(s).map_err(E::custom)?;
+                if a.signum() < 0 {
+                    return Err(E::custom(NegativeAmount(a)));
+                }
+
+                Ok(a)
+            }
+        }
+
+        deserializer.deserialize_str(AmountVisitor)
+    }
 }
 
 mod positive_amount {
@@ -156,10 +248,68 @@ mod positive_amount {
     }
 }
 
+mod rounding {
+    use fixnum::{ArithmeticError, ops::One};
+
+    use super::*;
+
+    /// The bits of a [`PreciseAmount`] per a bit of an [`Amount`].
+    const PRECISE_PER_AMOUNT_BITS: i128 = 10i128.pow(18 - 4);
+
+    impl Rounding {
+        /// Multiply an amount by a precise factor, rounding the product (only)
+        /// according to `self`.
+        pub fn mul(self, amount: Amount, factor: PreciseAmount) -> Result<Amount, ArithmeticError> {
+            let product = amount
+                .into_bits()
+                .checked_mul(factor.into_bits())
+                .ok_or(ArithmeticError::Overflow)?;
+            Ok(Amount::from_bits(
+                self.div(product, PreciseAmount::ONE.into_bits()),
+            ))
+        }
+
+        /// Round the precise amount to the [`Amount`]'s precision according to
+        /// `self`; also yields the adjustment made (the rounded amount less the
+        /// precise one).
+        pub fn round(self, precise: PreciseAmount) -> (Amount, PreciseAmount) {
+            let rounded = self.div(precise.into_bits(), PRECISE_PER_AMOUNT_BITS);
+            // less than a bit of an amount: fits, although the rounded amount
+            // scaled up may not (hence the wrapping arithmetic).
+            let adjustment = rounded
+                .wrapping_mul(PRECISE_PER_AMOUNT_BITS)
+                .wrapping_sub(precise.into_bits());
+            (
+                Amount::from_bits(rounded),
+                PreciseAmount::from_bits(adjustment),
+            )
+        }
+
+        /// Divide two integers, rounding the quotient according to `self`.
+        pub(crate) fn div(self, numerator: i128, denominator: i128) -> i128 {
+            let quotient = numerator / denominator;
+            let remainder = (numerator % denominator).unsigned_abs();
+            let complement = denominator.unsigned_abs() - remainder;
+            let away_from_zero = match self {
+                Self::Truncate => false,
+                Self::HalfUp => remainder >= complement,
+                Self::HalfEven => {
+                    remainder > complement || (remainder == complement && quotient % 2 != 0)
+                }
+            };
+            if away_from_zero {
+                quotient + (numerator.signum() * denominator.signum())
+            } else {
+                quotient
+            }
+        }
+    }
+}
+
 mod client_id {
     use std::fmt;
 
-    use crate::types::ClientId;
+    use crate::types::{ClientId, ClientIdRepr};
 
     impl fmt::Display for ClientId {
         fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -167,18 +317,31 @@ mod client_id {
         }
     }
 
-    #[cfg(test)]
     impl From<u16> for ClientId {
+        #[allow(clippy::useless_conversion)] // the same, unless `wide-client-ids`
         fn from(id: u16) -> Self {
+            Self(id.into())
+        }
+    }
+
+    #[cfg(feature = "wide-client-ids")]
+    impl From<u32> for ClientId {
+        fn from(id: u32) -> Self {
             Self(id)
         }
     }
+
+    impl From<ClientId> for ClientIdRepr {
+        fn from(client_id: ClientId) -> Self {
+            client_id.0
+        }
+    }
 }
 
 mod tx_id {
     use std::fmt;
 
-    use crate::types::TxId;
+    use crate::types::{TxId, TxIdRepr};
 
     impl fmt::Display for TxId {
         fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -186,10 +349,68 @@ mod tx_id {
         }
     }
 
-    #[cfg(test)]
     impl From<u32> for TxId {
+        #[allow(clippy::useless_conversion)] // the same, unless `wide-tx-ids`
         fn from(id: u32) -> Self {
+            Self(id.into())
+        }
+    }
+
+    #[cfg(feature = "wide-tx-ids")]
+    impl From<u64> for TxId {
+        fn from(id: u64) -> Self {
             Self(id)
         }
     }
+
+    impl From<TxId> for TxIdRepr {
+        fn from(tx_id: TxId) -> Self {
+            tx_id.0
+        }
+    }
+
+    #[cfg(not(feature = "wide-tx-ids"))]
+    impl From<TxId> for u64 {
+        fn from(tx_id: TxId) -> Self {
+            tx_id.0.into()
+        }
+    }
+
+    impl TxId {
+        /// The tx-id following this one, if any.
+        pub fn checked_next(self) -> Option<Self> {
+            self.0.checked_add(1).map(Self)
+        }
+    }
+}
+
+mod timestamp {
+    use std::fmt;
+
+    use crate::types::Timestamp;
+
+    impl fmt::Display for Timestamp {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "@{}", self.0)
+        }
+    }
+
+    impl From<u64> for Timestamp {
+        fn from(secs: u64) -> Self {
+            Self(secs)
+        }
+    }
+
+    impl From<Timestamp> for u64 {
+        fn from(timestamp: Timestamp) -> Self {
+            timestamp.0
+        }
+    }
+
+    impl Timestamp {
+        /// Seconds elapsed since `earlier`; zero if `earlier` is not earlier.
+        pub fn secs_since(self, earlier: Self) -> u64 {
+            self.0.saturating_sub(earlier.0)
+        }
+    }
 }
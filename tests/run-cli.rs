@@ -2,23 +2,86 @@ use std::{path::Path, process::Stdio};
 
 use test_case::test_case;
 
-#[test_case(20, "empty")]
-#[test_case(20, "case-01")]
-#[test_case(20, "case-02")]
-#[test_case(20, "case-03")]
-#[test_case(3, "case-04")]
-fn run_it(lru_cache_size: usize, case_name: &str) {
+#[test_case(20, None, None, "csv", "empty")]
+#[test_case(20, None, None, "csv", "case-01")]
+#[test_case(20, None, None, "csv", "case-02")]
+#[test_case(20, None, None, "csv", "case-03")]
+#[test_case(3, None, None, "csv", "case-04")]
+#[test_case(20, None, None, "csv", "case-05")]
+#[test_case(20, None, None, "csv", "case-06")]
+#[test_case(20, None, None, "csv", "case-07")]
+#[test_case(20, None, None, "csv", "case-08")]
+#[test_case(20, None, None, "csv", "case-09")]
+#[test_case(20, Some(10), None, "csv", "case-06.reordered")]
+#[test_case(3, None, Some(2), "csv", "case-04.pipelined")]
+#[test_case(3, None, None, "bin", "case-04.binary")]
+#[test_case(20, None, None, "jsonl", "case-05.jsonl")]
+fn run_it(
+    lru_cache_size: usize,
+    reorder_window_opt: Option<u64>,
+    parser_threads_opt: Option<usize>,
+    input_format: &str,
+    case_name: &str,
+) {
     #[cfg(debug_assertions)]
     const RELEASE_OPT: Option<&str> = None;
     #[cfg(not(debug_assertions))]
     const RELEASE_OPT: Option<&str> = Some("--release");
 
+    let input_name = case_name
+        .split('.')
+        .next()
+        .expect("split yields at least one");
     let input_file = Path::new(file!())
         .parent()
         .expect("file!().parent")
         .join("cases")
-        .join(format!("{}.csv", case_name));
-    let child = std::process::Command::new("cargo")
+        .join(format!("{}.csv", input_name));
+    let credit_limits_file = input_file.with_extension("credit-limits.csv");
+    let input_config_file = input_file.with_extension("input-config.toml");
+    let engine_config_file = input_file.with_extension("engine-config.toml");
+    let statements_file =
+        Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!("{}.statements.csv", case_name));
+    let input_file = match input_format {
+        // converted from the CSV-file
+        "bin" => {
+            let binary_file =
+                Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!("{}.bin", case_name));
+            let status = std::process::Command::new("cargo")
+                .arg("run")
+                .args(RELEASE_OPT)
+                .arg("--")
+                .arg("convert")
+                .arg(&input_file)
+                .arg(&binary_file)
+                .stdin(Stdio::null())
+                .status()
+                .expect("Command::status");
+            assert!(status.success());
+            binary_file
+        }
+        "jsonl" => input_file.with_extension("jsonl"),
+        _ => input_file,
+    };
+
+    let mut command = std::process::Command::new("cargo");
+    if credit_limits_file.exists() {
+        command.env("CREDIT_LIMITS", credit_limits_file);
+    }
+    if input_config_file.exists() {
+        command.env("INPUT_CONFIG", input_config_file);
+    }
+    if engine_config_file.exists() {
+        command.env("ENGINE_CONFIG", &engine_config_file);
+        command.env("STATEMENTS_PATH", &statements_file);
+    }
+    if let Some(reorder_window) = reorder_window_opt {
+        command.env("REORDER_WINDOW", reorder_window.to_string());
+    }
+    if let Some(parser_threads) = parser_threads_opt {
+        command.env("PARSER_THREADS", parser_threads.to_string());
+    }
+    let child = command
         .arg("run")
         .args(RELEASE_OPT)
         .arg("--")
@@ -37,10 +100,17 @@ fn run_it(lru_cache_size: usize, case_name: &str) {
     if output_lines.len() > 1 {
         output_lines[1..].sort();
     }
+    let mut output = output_lines.join("\n");
+    if engine_config_file.exists()
+        && let Ok(statements) = std::fs::read_to_string(&statements_file)
+    {
+        output.push_str("\n\nstatements:\n");
+        output.push_str(statements.trim_end());
+    }
     insta::with_settings!({
         snapshot_path => "cases",
         prepend_module_to_snapshot => false,
     }, {
-        insta::assert_snapshot!(case_name, output_lines.join("\n"));
+        insta::assert_snapshot!(case_name, output);
     });
 }